@@ -54,14 +54,14 @@ pub fn call(s: Structure) -> TokenStream {
 
         /// Call extension trait.
         pub trait #call_trait<T: #subxt::Runtime + #module> {
-            /// Create and submit an extrinsic.
+            /// Create and submit an extrinsic, paying the tip of the signer if set.
             fn #call<'a>(
                 &'a self,
                 signer: &'a (dyn #subxt::Signer<T> + Send + Sync),
                 #args
             ) -> core::pin::Pin<Box<dyn core::future::Future<Output = Result<T::Hash, #subxt::Error>> + Send + 'a>>;
 
-            /// Create, submit and watch an extrinsic.
+            /// Create, submit and watch an extrinsic, paying the tip of the signer if set.
             fn #call_and_watch<'a>(
                 &'a self,
                 signer: &'a (dyn #subxt::Signer<T> + Send + Sync),
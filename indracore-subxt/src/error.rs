@@ -15,11 +15,24 @@
 // along with substrate-subxt.  If not, see <http://www.gnu.org/licenses/>.
 
 use jsonrpsee::{client::RequestError, transport::ws::WsNewDnsError};
+use serde_json::Value as JsonValue;
 use sp_core::crypto::SecretStringError;
-use sp_runtime::{transaction_validity::TransactionValidityError, DispatchError};
+use sp_runtime::{
+    transaction_validity::{InvalidTransaction, TransactionValidityError, UnknownTransaction},
+    DispatchError,
+};
+use std::{convert::TryFrom, time::Duration};
 use thiserror::Error;
 
-use crate::metadata::{Metadata, MetadataError};
+use crate::{
+    metadata::{Metadata, MetadataError},
+    rpc::TransactionStatus,
+};
+
+/// Error code of `author_submitExtrinsic` for an invalid transaction.
+const POOL_INVALID_TX: i64 = 1010;
+/// Error code of `author_submitExtrinsic` for a transaction of unknown validity.
+const POOL_UNKNOWN_VALIDITY: i64 = 1011;
 
 /// Error enum.
 #[derive(Debug, Error)]
@@ -61,6 +74,12 @@ pub enum Error {
     /// Runtime error.
     #[error("Runtime error: {0}")]
     Runtime(#[from] RuntimeError),
+    /// Transaction pool error.
+    #[error("Transaction error: {0}")]
+    Transaction(#[from] TransactionError),
+    /// Events of an extrinsic not received in time.
+    #[error("Events of the extrinsic not received within {0:?}")]
+    EventsTimeout(Duration),
     /// Other error.
     #[error("Other error: {0}")]
     Other(String),
@@ -78,6 +97,17 @@ impl From<TransactionValidityError> for Error {
     }
 }
 
+impl Error {
+    /// Converts an error of `author_submitExtrinsic` into `Error::Invalid` if the pool rejected
+    /// the transaction.
+    pub(crate) fn from_submission(error: RequestError) -> Self {
+        match pool_validity(&error) {
+            Some(validity) => Error::Invalid(validity),
+            None => Error::Rpc(error),
+        }
+    }
+}
+
 impl From<&str> for Error {
     fn from(error: &str) -> Self {
         Error::Other(error.into())
@@ -90,6 +120,147 @@ impl From<String> for Error {
     }
 }
 
+/// Decodes the validity error the pool attaches to a rejected submission.
+fn pool_validity(error: &RequestError) -> Option<TransactionValidityError> {
+    let error = match error {
+        RequestError::Request(error) => error,
+        _ => return None,
+    };
+    let data = error.data.as_ref()?;
+    match error.code.code() {
+        POOL_INVALID_TX => invalid_transaction(data).map(Into::into),
+        POOL_UNKNOWN_VALIDITY => unknown_transaction(data).map(Into::into),
+        _ => None,
+    }
+}
+
+/// The pool reports the reason either as its description or serialized.
+fn invalid_transaction(data: &JsonValue) -> Option<InvalidTransaction> {
+    if let Some(code) = custom_code(data) {
+        return Some(InvalidTransaction::Custom(code));
+    }
+    let reason = match data.as_str()? {
+        "Call" | "Transaction call is not expected" => InvalidTransaction::Call,
+        "Payment" | "Inability to pay some fees (e.g. account balance too low)" => {
+            InvalidTransaction::Payment
+        }
+        "Future" | "Transaction will be valid in the future" => InvalidTransaction::Future,
+        "Stale" | "Transaction is outdated" => InvalidTransaction::Stale,
+        "BadProof" | "Transaction has a bad signature" => InvalidTransaction::BadProof,
+        "AncientBirthBlock" | "Transaction has an ancient birth block" => {
+            InvalidTransaction::AncientBirthBlock
+        }
+        "ExhaustsResources" | "Transaction would exhausts the block limits" => {
+            InvalidTransaction::ExhaustsResources
+        }
+        "BadMandatory" | "A call was labelled as mandatory, but resulted in an Error." => {
+            InvalidTransaction::BadMandatory
+        }
+        "MandatoryDispatch"
+        | "Transaction dispatch is mandatory; transactions may not have mandatory dispatches." => {
+            InvalidTransaction::MandatoryDispatch
+        }
+        _ => return None,
+    };
+    Some(reason)
+}
+
+fn unknown_transaction(data: &JsonValue) -> Option<UnknownTransaction> {
+    if let Some(code) = custom_code(data) {
+        return Some(UnknownTransaction::Custom(code));
+    }
+    let reason = match data.as_str()? {
+        "CannotLookup" | "Could not lookup information required to validate the transaction" => {
+            UnknownTransaction::CannotLookup
+        }
+        "NoUnsignedValidator"
+        | "Could not find an unsigned validator for the unsigned transaction" => {
+            UnknownTransaction::NoUnsignedValidator
+        }
+        _ => return None,
+    };
+    Some(reason)
+}
+
+fn custom_code(data: &JsonValue) -> Option<u8> {
+    match data {
+        JsonValue::Object(map) => u8::try_from(map.get("Custom")?.as_u64()?).ok(),
+        JsonValue::String(reason) => reason.strip_prefix("Custom error: ")?.parse().ok(),
+        _ => None,
+    }
+}
+
+/// Final status of an extrinsic the pool will not include it after.
+#[derive(Clone, Debug, Eq, Error, PartialEq)]
+pub enum TransactionError {
+    /// The extrinsic is no longer valid in the current state.
+    #[error("Extrinsic {extrinsic} is invalid")]
+    Invalid {
+        /// Hex encoded hash of the extrinsic.
+        extrinsic: String,
+    },
+    /// The extrinsic was dropped from the pool because of its limit.
+    #[error("Extrinsic {extrinsic} was dropped")]
+    Dropped {
+        /// Hex encoded hash of the extrinsic.
+        extrinsic: String,
+    },
+    /// The extrinsic was replaced by another one providing the same tags, e.g. the same
+    /// sender and nonce.
+    #[error("Extrinsic {extrinsic} was usurped by {usurper}")]
+    Usurped {
+        /// Hex encoded hash of the extrinsic.
+        extrinsic: String,
+        /// Hex encoded hash of the extrinsic replacing it.
+        usurper: String,
+    },
+    /// The node stopped watching the finality of the block the extrinsic is in.
+    #[error("Finality timeout for extrinsic {extrinsic} in block {block}")]
+    FinalityTimeout {
+        /// Hex encoded hash of the extrinsic.
+        extrinsic: String,
+        /// Hex encoded hash of the block.
+        block: String,
+    },
+}
+
+impl TransactionError {
+    /// Returns the error for a final status other than `Finalized`.
+    pub fn from_status<H: AsRef<[u8]>>(
+        extrinsic: H,
+        status: &TransactionStatus<H, H>,
+    ) -> Option<Self> {
+        let extrinsic = to_hex(extrinsic);
+        match status {
+            TransactionStatus::Invalid => Some(Self::Invalid { extrinsic }),
+            TransactionStatus::Dropped => Some(Self::Dropped { extrinsic }),
+            TransactionStatus::Usurped(usurper) => Some(Self::Usurped {
+                extrinsic,
+                usurper: to_hex(usurper),
+            }),
+            TransactionStatus::FinalityTimeout(block) => Some(Self::FinalityTimeout {
+                extrinsic,
+                block: to_hex(block),
+            }),
+            _ => None,
+        }
+    }
+
+    /// Returns the hex encoded hash of the extrinsic.
+    pub fn extrinsic(&self) -> &str {
+        match self {
+            Self::Invalid { extrinsic }
+            | Self::Dropped { extrinsic }
+            | Self::Usurped { extrinsic, .. }
+            | Self::FinalityTimeout { extrinsic, .. } => extrinsic,
+        }
+    }
+}
+
+fn to_hex<H: AsRef<[u8]>>(hash: H) -> String {
+    format!("0x{}", hex::encode(hash.as_ref()))
+}
+
 /// Runtime error.
 #[derive(Clone, Debug, Eq, Error, PartialEq)]
 pub enum RuntimeError {
@@ -142,6 +313,8 @@ impl RuntimeError {
 #[derive(Clone, Debug, Eq, Error, PartialEq)]
 #[error("{error} from {module}")]
 pub struct ModuleError {
+    /// Name of the module that raised the error.
     pub module: String,
+    /// Name of the error variant.
     pub error: String,
 }
@@ -18,7 +18,7 @@ use codec::{Codec, Compact, Decode, Encode, Input, Output};
 use frame_support::dispatch::DispatchInfo;
 use sp_runtime::{DispatchError, DispatchResult};
 use std::{
-    collections::{HashMap, HashSet},
+    collections::{BTreeMap, HashMap, HashSet},
     fmt,
     marker::{PhantomData, Send},
 };
@@ -279,3 +279,49 @@ pub enum Raw {
     /// Error
     Error(RuntimeError),
 }
+
+/// Every event of a block in the order it was emitted.
+///
+/// A failed extrinsic shows up as `Raw::Error` in place of its `System.ExtrinsicFailed` event.
+#[derive(Debug)]
+pub struct BlockEvents<T: System> {
+    /// Block hash.
+    pub block: T::Hash,
+    /// Events with the phase they were emitted in.
+    pub events: Vec<(Phase, Raw)>,
+}
+
+impl<T: System> BlockEvents<T> {
+    /// Returns the events emitted while initializing the block.
+    pub fn initialization(&self) -> impl Iterator<Item = &Raw> {
+        self.in_phase(Phase::Initialization)
+    }
+
+    /// Returns the events emitted by the extrinsic with `index`.
+    pub fn extrinsic(&self, index: u32) -> impl Iterator<Item = &Raw> {
+        self.in_phase(Phase::ApplyExtrinsic(index))
+    }
+
+    /// Returns the events emitted while finalizing the block.
+    pub fn finalization(&self) -> impl Iterator<Item = &Raw> {
+        self.in_phase(Phase::Finalization)
+    }
+
+    /// Returns the events of the extrinsics grouped by extrinsic index.
+    pub fn by_extrinsic(&self) -> BTreeMap<u32, Vec<&Raw>> {
+        let mut extrinsics = BTreeMap::<u32, Vec<&Raw>>::new();
+        for (phase, raw) in &self.events {
+            if let Phase::ApplyExtrinsic(index) = phase {
+                extrinsics.entry(*index).or_default().push(raw);
+            }
+        }
+        extrinsics
+    }
+
+    fn in_phase(&self, phase: Phase) -> impl Iterator<Item = &Raw> {
+        self.events
+            .iter()
+            .filter(move |(p, _)| *p == phase)
+            .map(|(_, raw)| raw)
+    }
+}
@@ -14,20 +14,29 @@
 // You should have received a copy of the GNU General Public License
 // along with substrate-subxt.  If not, see <http://www.gnu.org/licenses/>.
 
-use codec::{Decode, Encode};
+use codec::{Decode, Encode, Input};
 use core::{fmt::Debug, marker::PhantomData};
 use sp_runtime::{
-    generic::Era, traits::SignedExtension, transaction_validity::TransactionValidityError,
+    generic::Era,
+    traits::{SignedExtension, UniqueSaturatedInto},
+    transaction_validity::TransactionValidityError,
 };
 
 use crate::{
     frame::{balances::Balances, system::System},
+    metadata::MetadataError,
     runtimes::Runtime,
+    Encoded,
 };
+use sp_runtime::transaction_validity::UnknownTransaction;
+use std::{collections::HashMap, sync::Arc};
 
 /// Extra type.
 pub type Extra<T> = <<T as Runtime>::Extra as SignedExtra<T>>::Extra;
 
+/// Tip type.
+pub type Tip<T> = <<T as Runtime>::Extra as SignedExtra<T>>::Tip;
+
 /// SignedExtra checks copied from substrate, in order to remove requirement to implement
 /// substrate's `frame_system::Trait`
 
@@ -121,9 +130,9 @@ where
 ///
 /// # Note
 ///
-/// This is modified from the substrate version to allow passing in of the genesis hash, which is
-/// returned via `additional_signed()`. It assumes therefore `Era::Immortal` (The transaction is
-/// valid forever)
+/// This is modified from the substrate version to allow passing in of the checkpoint hash, which
+/// is returned via `additional_signed()`. It is the genesis hash for `Era::Immortal` and the hash
+/// of the block the era starts from for a mortal era.
 #[derive(Encode, Decode, Clone, Eq, PartialEq, Debug)]
 pub struct CheckEra<T: System>(
     /// The default structure for the Extra encoding
@@ -137,7 +146,7 @@ impl<T> SignedExtension for CheckEra<T>
 where
     T: System + Clone + Debug + Eq + Send + Sync,
 {
-    const IDENTIFIER: &'static str = "CheckEra";
+    const IDENTIFIER: &'static str = "CheckMortality";
     type AccountId = u64;
     type Call = ();
     type AdditionalSigned = T::Hash;
@@ -207,9 +216,41 @@ pub trait SignedExtra<T: System>: SignedExtension {
     /// The type the extras.
     type Extra: SignedExtension + Send + Sync;
 
+    /// The type of the tip paid to the block author, the balance of runtimes charging for
+    /// transactions.
+    type Tip: Copy + Debug + Send + Sync + 'static;
+
     /// Creates a new `SignedExtra`.
     fn new(spec_version: u32, tx_version: u32, nonce: T::Index, genesis_hash: T::Hash) -> Self;
 
+    /// Sets the era of the transaction and the hash of the block it starts from.
+    ///
+    /// Extras without a mortality check ignore the era.
+    fn set_era(&mut self, _era: Era, _checkpoint: T::Hash) {}
+
+    /// Sets the tip paid to the block author.
+    ///
+    /// Extras without a payment check ignore the tip.
+    fn set_tip(&mut self, _tip: Self::Tip) {}
+
+    /// Sets the registry signed extensions are built from.
+    ///
+    /// Extras with a fixed set of extensions ignore it.
+    fn set_extension_registry(&mut self, _registry: Arc<ExtensionRegistry<T>>) {}
+
+    /// Sets the signed extensions of the runtime, as listed in the metadata.
+    ///
+    /// Extras with a fixed set of extensions ignore them, they are checked against
+    /// `identifiers` when the client is built.
+    fn set_signed_extensions(&mut self, _identifiers: &[String]) -> Result<(), MetadataError> {
+        Ok(())
+    }
+
+    /// Returns the identifiers of the extensions in encoding order.
+    fn identifiers(&self) -> Vec<&'static str> {
+        <Self::Extra as SignedExtension>::identifier()
+    }
+
     /// Returns the transaction extra.
     fn extra(&self) -> Self::Extra;
 }
@@ -221,6 +262,9 @@ pub struct DefaultExtra<T: System> {
     tx_version: u32,
     nonce: T::Index,
     genesis_hash: T::Hash,
+    era: Era,
+    checkpoint: T::Hash,
+    tip: u128,
 }
 
 impl<T: System + Balances + Clone + Debug + Eq + Send + Sync> SignedExtra<T> for DefaultExtra<T> {
@@ -233,6 +277,7 @@ impl<T: System + Balances + Clone + Debug + Eq + Send + Sync> SignedExtra<T> for
         CheckWeight<T>,
         ChargeTransactionPayment<T>,
     );
+    type Tip = T::Balance;
 
     fn new(spec_version: u32, tx_version: u32, nonce: T::Index, genesis_hash: T::Hash) -> Self {
         DefaultExtra {
@@ -240,18 +285,30 @@ impl<T: System + Balances + Clone + Debug + Eq + Send + Sync> SignedExtra<T> for
             tx_version,
             nonce,
             genesis_hash,
+            era: Era::Immortal,
+            checkpoint: genesis_hash,
+            tip: 0,
         }
     }
 
+    fn set_era(&mut self, era: Era, checkpoint: T::Hash) {
+        self.era = era;
+        self.checkpoint = checkpoint;
+    }
+
+    fn set_tip(&mut self, tip: T::Balance) {
+        self.tip = tip.unique_saturated_into();
+    }
+
     fn extra(&self) -> Self::Extra {
         (
             CheckSpecVersion(PhantomData, self.spec_version),
             CheckTxVersion(PhantomData, self.tx_version),
             CheckGenesis(PhantomData, self.genesis_hash),
-            CheckEra((Era::Immortal, PhantomData), self.genesis_hash),
+            CheckEra((self.era, PhantomData), self.checkpoint),
             CheckNonce(self.nonce),
             CheckWeight(PhantomData),
-            ChargeTransactionPayment(<T as Balances>::Balance::default()),
+            ChargeTransactionPayment(self.tip.unique_saturated_into()),
         )
     }
 }
@@ -267,3 +324,437 @@ impl<T: System + Balances + Clone + Debug + Eq + Send + Sync> SignedExtension fo
         self.extra().additional_signed()
     }
 }
+
+/// Parameters the extensions of a [`DynamicExtra`] are built from.
+#[derive(Encode, Decode, Clone, Eq, PartialEq, Debug)]
+pub struct ExtraParams<T: System> {
+    /// Spec version of the runtime.
+    pub spec_version: u32,
+    /// Transaction version of the runtime.
+    pub tx_version: u32,
+    /// Nonce of the signer.
+    pub nonce: T::Index,
+    /// Genesis hash of the chain.
+    pub genesis_hash: T::Hash,
+    /// Era of the transaction.
+    pub era: Era,
+    /// Hash of the block the era starts from, the genesis hash for `Era::Immortal`.
+    pub checkpoint: T::Hash,
+    /// Tip paid to the block author.
+    pub tip: u128,
+}
+
+/// Builds the encoded extra and additional signed data of a signed extension.
+pub type ExtensionBuilder<T> =
+    fn(&ExtraParams<T>) -> Result<(Vec<u8>, Vec<u8>), TransactionValidityError>;
+
+/// Decodes the extra data of a signed extension and returns its encoding.
+pub type ExtensionDecoder = fn(&mut dyn Input) -> Result<Vec<u8>, codec::Error>;
+
+/// Encodes the extra and additional signed data of `extension`.
+pub fn encode_extension<E: SignedExtension>(
+    extension: E,
+) -> Result<(Vec<u8>, Vec<u8>), TransactionValidityError> {
+    Ok((extension.encode(), extension.additional_signed()?.encode()))
+}
+
+/// Decodes the extra data of the extension `E` and returns its encoding.
+pub fn decode_extension<E: Encode + Decode>(
+    input: &mut dyn Input,
+) -> Result<Vec<u8>, codec::Error> {
+    Ok(E::decode(&mut DynInput(input))?.encode())
+}
+
+/// Sized `Input` over a trait object, so an `ExtensionDecoder` can be a plain function.
+struct DynInput<'a>(&'a mut dyn Input);
+
+impl<'a> Input for DynInput<'a> {
+    fn remaining_len(&mut self) -> Result<Option<usize>, codec::Error> {
+        self.0.remaining_len()
+    }
+
+    fn read(&mut self, into: &mut [u8]) -> Result<(), codec::Error> {
+        self.0.read(into)
+    }
+}
+
+/// Signed extensions known to the client, keyed by `SignedExtension::IDENTIFIER`.
+///
+/// Extensions the default registry does not know are registered on a copy of it and handed to
+/// `ClientBuilder::set_extension_registry` or `OfflineBuilder::set_extension_registry`.
+pub struct ExtensionRegistry<T: System> {
+    extensions: HashMap<&'static str, (ExtensionBuilder<T>, ExtensionDecoder)>,
+}
+
+impl<T: System> ExtensionRegistry<T> {
+    /// Creates an empty registry.
+    pub fn empty() -> Self {
+        Self {
+            extensions: HashMap::new(),
+        }
+    }
+
+    /// Registers the builder and decoder of the extension named `identifier`.
+    pub fn register(
+        &mut self,
+        identifier: &'static str,
+        builder: ExtensionBuilder<T>,
+        decoder: ExtensionDecoder,
+    ) {
+        self.extensions.insert(identifier, (builder, decoder));
+    }
+
+    /// Returns the static identifier and builder of the extension named `identifier`.
+    pub fn get(&self, identifier: &str) -> Option<(&'static str, ExtensionBuilder<T>)> {
+        self.extensions
+            .get_key_value(identifier)
+            .map(|(identifier, (builder, _))| (*identifier, *builder))
+    }
+
+    /// Decodes the extra data of the extensions named `identifiers`, in order.
+    pub fn decode<I: Input>(
+        &self,
+        identifiers: &[String],
+        input: &mut I,
+    ) -> Result<Vec<u8>, codec::Error> {
+        let mut extra = Vec::new();
+        for identifier in identifiers {
+            let (_, decode) = self
+                .extensions
+                .get(identifier.as_str())
+                .ok_or("Unknown signed extension")?;
+            extra.extend(decode(input)?);
+        }
+        Ok(extra)
+    }
+}
+
+impl<T: System> Clone for ExtensionRegistry<T> {
+    fn clone(&self) -> Self {
+        Self {
+            extensions: self.extensions.clone(),
+        }
+    }
+}
+
+impl<T: System> Debug for ExtensionRegistry<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let mut identifiers: Vec<_> = self.extensions.keys().collect();
+        identifiers.sort();
+        f.debug_struct("ExtensionRegistry")
+            .field("extensions", &identifiers)
+            .finish()
+    }
+}
+
+/// Registries are equal if they know the same extensions.
+impl<T: System> PartialEq for ExtensionRegistry<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.extensions.len() == other.extensions.len()
+            && self
+                .extensions
+                .keys()
+                .all(|identifier| other.extensions.contains_key(identifier))
+    }
+}
+
+impl<T: System> Eq for ExtensionRegistry<T> {}
+
+impl<T: System + Balances + Clone + Debug + Eq + Send + Sync> Default for ExtensionRegistry<T> {
+    /// Registers the extensions of `DefaultExtra`, with `CheckEra` as an alias of
+    /// `CheckMortality` for runtimes predating the rename.
+    fn default() -> Self {
+        let mut registry = Self::empty();
+        registry.register(
+            "CheckSpecVersion",
+            |p| encode_extension(CheckSpecVersion::<T>(PhantomData, p.spec_version)),
+            decode_extension::<CheckSpecVersion<T>>,
+        );
+        registry.register(
+            "CheckTxVersion",
+            |p| encode_extension(CheckTxVersion::<T>(PhantomData, p.tx_version)),
+            decode_extension::<CheckTxVersion<T>>,
+        );
+        registry.register(
+            "CheckGenesis",
+            |p| encode_extension(CheckGenesis::<T>(PhantomData, p.genesis_hash)),
+            decode_extension::<CheckGenesis<T>>,
+        );
+        registry.register(
+            "CheckMortality",
+            |p| encode_extension(CheckEra::<T>((p.era, PhantomData), p.checkpoint)),
+            decode_extension::<CheckEra<T>>,
+        );
+        registry.register(
+            "CheckEra",
+            |p| encode_extension(CheckEra::<T>((p.era, PhantomData), p.checkpoint)),
+            decode_extension::<CheckEra<T>>,
+        );
+        registry.register(
+            "CheckNonce",
+            |p| encode_extension(CheckNonce::<T>(p.nonce)),
+            decode_extension::<CheckNonce<T>>,
+        );
+        registry.register(
+            "CheckWeight",
+            |_| encode_extension(CheckWeight::<T>(PhantomData)),
+            decode_extension::<CheckWeight<T>>,
+        );
+        registry.register(
+            "ChargeTransactionPayment",
+            |p| encode_extension(ChargeTransactionPayment::<T>(p.tip.unique_saturated_into())),
+            decode_extension::<ChargeTransactionPayment<T>>,
+        );
+        registry
+    }
+}
+
+/// `SignedExtra` assembling the signed extensions listed in the runtime metadata from an
+/// [`ExtensionRegistry`], so added, removed or reordered extensions are picked up after a
+/// runtime upgrade.
+///
+/// Until `set_signed_extensions` is called it uses the extensions of `DefaultExtra`, and until
+/// `set_extension_registry` is called the default registry.
+#[derive(Encode, Decode, Clone, Eq, PartialEq, Debug)]
+pub struct DynamicExtra<T: System> {
+    params: ExtraParams<T>,
+    identifiers: Vec<String>,
+    #[codec(skip)]
+    registry: Arc<ExtensionRegistry<T>>,
+}
+
+impl<T: System + Balances + Clone + Debug + Eq + Send + Sync> SignedExtra<T> for DynamicExtra<T> {
+    type Extra = DynamicExtension<T>;
+    type Tip = T::Balance;
+
+    fn new(spec_version: u32, tx_version: u32, nonce: T::Index, genesis_hash: T::Hash) -> Self {
+        DynamicExtra {
+            params: ExtraParams {
+                spec_version,
+                tx_version,
+                nonce,
+                genesis_hash,
+                era: Era::Immortal,
+                checkpoint: genesis_hash,
+                tip: 0,
+            },
+            identifiers: default_identifiers::<T>(),
+            registry: Default::default(),
+        }
+    }
+
+    fn set_era(&mut self, era: Era, checkpoint: T::Hash) {
+        self.params.era = era;
+        self.params.checkpoint = checkpoint;
+    }
+
+    fn set_tip(&mut self, tip: T::Balance) {
+        self.params.tip = tip.unique_saturated_into();
+    }
+
+    fn set_extension_registry(&mut self, registry: Arc<ExtensionRegistry<T>>) {
+        self.registry = registry;
+    }
+
+    fn set_signed_extensions(&mut self, identifiers: &[String]) -> Result<(), MetadataError> {
+        if let Some(unknown) = identifiers
+            .iter()
+            .find(|id| self.registry.get(id).is_none())
+        {
+            return Err(MetadataError::SignedExtensionNotSupported(unknown.clone()));
+        }
+        self.identifiers = identifiers.to_vec();
+        Ok(())
+    }
+
+    fn identifiers(&self) -> Vec<&'static str> {
+        self.identifiers
+            .iter()
+            .filter_map(|id| self.registry.get(id).map(|(identifier, _)| identifier))
+            .collect()
+    }
+
+    fn extra(&self) -> Self::Extra {
+        let mut extra = Vec::new();
+        let mut additional = Ok(Vec::new());
+        for identifier in &self.identifiers {
+            match self
+                .registry
+                .get(identifier)
+                .map(|(_, build)| build(&self.params))
+            {
+                Some(Ok((ext, add))) => {
+                    extra.extend(ext);
+                    if let Ok(additional) = &mut additional {
+                        additional.extend(add);
+                    }
+                }
+                Some(Err(err)) => additional = Err(err),
+                None => additional = Err(UnknownTransaction::CannotLookup.into()),
+            }
+        }
+        DynamicExtension {
+            extra,
+            additional,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T: System + Balances + Clone + Debug + Eq + Send + Sync> SignedExtension for DynamicExtra<T> {
+    const IDENTIFIER: &'static str = "DynamicExtra";
+    type AccountId = T::AccountId;
+    type Call = ();
+    type AdditionalSigned = Encoded;
+    type Pre = ();
+
+    fn additional_signed(&self) -> Result<Self::AdditionalSigned, TransactionValidityError> {
+        self.extra().additional_signed()
+    }
+}
+
+fn default_identifiers<T: System + Balances + Clone + Debug + Eq + Send + Sync>() -> Vec<String> {
+    <<DefaultExtra<T> as SignedExtra<T>>::Extra as SignedExtension>::identifier()
+        .into_iter()
+        .map(String::from)
+        .collect()
+}
+
+/// Encoded extensions assembled by a [`DynamicExtra`].
+///
+/// The encoding does not say which extensions it holds, so an extension decoded as part of an
+/// extrinsic is assumed to hold the extensions of `DefaultExtra`, decoded through the default
+/// [`ExtensionRegistry`]. The additional signed data is not part of the encoding and is
+/// unavailable after decoding.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct DynamicExtension<T: System> {
+    extra: Vec<u8>,
+    additional: Result<Vec<u8>, TransactionValidityError>,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T: System> Encode for DynamicExtension<T> {
+    fn encode(&self) -> Vec<u8> {
+        self.extra.clone()
+    }
+}
+
+impl<T: System + Balances + Clone + Debug + Eq + Send + Sync> Decode for DynamicExtension<T> {
+    fn decode<I: Input>(input: &mut I) -> Result<Self, codec::Error> {
+        let extra = ExtensionRegistry::<T>::default().decode(&default_identifiers::<T>(), input)?;
+        Ok(DynamicExtension {
+            extra,
+            additional: Err(UnknownTransaction::CannotLookup.into()),
+            _marker: PhantomData,
+        })
+    }
+}
+
+impl<T: System + Balances + Clone + Debug + Eq + Send + Sync> SignedExtension
+    for DynamicExtension<T>
+{
+    const IDENTIFIER: &'static str = "DynamicExtension";
+    type AccountId = u64;
+    type Call = ();
+    type AdditionalSigned = Encoded;
+    type Pre = ();
+
+    fn additional_signed(&self) -> Result<Self::AdditionalSigned, TransactionValidityError> {
+        self.additional.clone().map(Encoded)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtimes::IndracoreRuntime;
+    use sp_core::H256;
+
+    fn extras() -> (
+        DefaultExtra<IndracoreRuntime>,
+        DynamicExtra<IndracoreRuntime>,
+    ) {
+        let genesis_hash = H256::repeat_byte(1);
+        let mut default = DefaultExtra::new(3, 2, 5, genesis_hash);
+        let mut dynamic = DynamicExtra::new(3, 2, 5, genesis_hash);
+        let era = Era::mortal(64, 100);
+        default.set_era(era, H256::repeat_byte(7));
+        dynamic.set_era(era, H256::repeat_byte(7));
+        default.set_tip(1_000);
+        dynamic.set_tip(1_000);
+        (default, dynamic)
+    }
+
+    fn identifiers(identifiers: &[&str]) -> Vec<String> {
+        identifiers.iter().map(|id| id.to_string()).collect()
+    }
+
+    #[test]
+    fn test_dynamic_extra_encodes_as_default_extra() {
+        let (default, mut dynamic) = extras();
+        let default_identifiers = identifiers(&default.identifiers());
+        dynamic.set_signed_extensions(&default_identifiers).unwrap();
+        assert_eq!(dynamic.identifiers(), default.identifiers());
+        assert_eq!(dynamic.extra().encode(), default.extra().encode());
+        assert_eq!(
+            dynamic.extra().additional_signed().unwrap().encode(),
+            default.extra().additional_signed().unwrap().encode()
+        );
+    }
+
+    #[test]
+    fn test_dynamic_extension_decodes() {
+        let (default, dynamic) = extras();
+        let encoded = default.extra().encode();
+        let mut input = &encoded[..];
+        let decoded = DynamicExtension::<IndracoreRuntime>::decode(&mut input).unwrap();
+        assert!(input.is_empty());
+        assert_eq!(decoded.encode(), dynamic.extra().encode());
+        let truncated = &encoded[..encoded.len() - 1];
+        assert!(DynamicExtension::<IndracoreRuntime>::decode(&mut &truncated[..]).is_err());
+    }
+
+    #[test]
+    fn test_dynamic_extra_follows_the_metadata() {
+        let (_, mut dynamic) = extras();
+        dynamic
+            .set_signed_extensions(&identifiers(&["CheckNonce", "CheckSpecVersion"]))
+            .unwrap();
+        assert_eq!(
+            dynamic.identifiers(),
+            vec!["CheckNonce", "CheckSpecVersion"]
+        );
+        let expected = (
+            CheckNonce::<IndracoreRuntime>(5),
+            CheckSpecVersion::<IndracoreRuntime>(PhantomData, 3),
+        );
+        assert_eq!(dynamic.extra().encode(), expected.encode());
+        assert_eq!(
+            dynamic.extra().additional_signed().unwrap().encode(),
+            expected.additional_signed().unwrap().encode()
+        );
+
+        let err = dynamic
+            .set_signed_extensions(&identifiers(&["CheckNonce", "CheckFoo"]))
+            .unwrap_err();
+        assert!(matches!(err, MetadataError::SignedExtensionNotSupported(id) if id == "CheckFoo"));
+    }
+
+    #[test]
+    fn test_registered_extension() {
+        let (_, mut dynamic) = extras();
+        let mut registry = ExtensionRegistry::<IndracoreRuntime>::default();
+        registry.register(
+            "CheckFoo",
+            |p| Ok((p.nonce.encode(), vec![7])),
+            decode_extension::<u32>,
+        );
+        dynamic.set_extension_registry(Arc::new(registry));
+        dynamic
+            .set_signed_extensions(&identifiers(&["CheckFoo", "CheckWeight"]))
+            .unwrap();
+        assert_eq!(dynamic.identifiers(), vec!["CheckFoo", "CheckWeight"]);
+        assert_eq!(dynamic.extra().encode(), 5u32.encode());
+        assert_eq!(dynamic.extra().additional_signed().unwrap().0, vec![7]);
+    }
+}
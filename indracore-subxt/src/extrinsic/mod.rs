@@ -17,18 +17,23 @@
 //! Create signed or unsigned extrinsics.
 
 mod extra;
+mod offline;
 mod signer;
 
 pub use self::{
     extra::{
-        ChargeTransactionPayment, CheckEra, CheckGenesis, CheckNonce, CheckSpecVersion,
-        CheckTxVersion, CheckWeight, DefaultExtra, Extra, SignedExtra,
+        decode_extension, encode_extension, ChargeTransactionPayment, CheckEra, CheckGenesis,
+        CheckNonce, CheckSpecVersion, CheckTxVersion, CheckWeight, DefaultExtra, DynamicExtension,
+        DynamicExtra, ExtensionBuilder, ExtensionDecoder, ExtensionRegistry, Extra, ExtraParams,
+        SignedExtra, Tip,
     },
-    signer::{PairSigner, Signer},
+    offline::{extrinsic_from_hex, extrinsic_to_hex, OfflineBuilder, UnsignedPayload},
+    signer::{PairSigner, RemoteSigner, Signer, SigningEndpoint},
 };
 
-use sp_runtime::traits::SignedExtension;
+use sp_runtime::{generic::Era, traits::SignedExtension};
 use sp_version::RuntimeVersion;
+use std::sync::Arc;
 
 use crate::{frame::system::System, runtimes::Runtime, Encoded, Error};
 
@@ -43,11 +48,41 @@ pub type UncheckedExtrinsic<T> = sp_runtime::generic::UncheckedExtrinsic<
 /// SignedPayload type.
 pub type SignedPayload<T> = sp_runtime::generic::SignedPayload<Encoded, Extra<T>>;
 
+/// Number of blocks a mortal transaction is valid for by default.
+pub const DEFAULT_ERA_PERIOD: u64 = 64;
+
+/// Lifetime of the transactions signed by a client.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Mortality {
+    /// The transaction is valid forever.
+    Immortal,
+    /// The transaction is valid for a period of blocks starting at the best block.
+    Best(u64),
+    /// The transaction is valid for a period of blocks starting at the finalized block.
+    Finalized(u64),
+}
+
+impl Default for Mortality {
+    fn default() -> Self {
+        Mortality::Finalized(DEFAULT_ERA_PERIOD)
+    }
+}
+
 /// Creates a signed extrinsic
+///
+/// `checkpoint` is the hash of the block a mortal `era` starts from, or the genesis hash for
+/// `Era::Immortal`. The tip of the signer, if any, is paid to the block author.
+/// `signed_extensions` are the identifiers listed in the runtime metadata, built from
+/// `extensions` or the default registry of the extra.
+#[allow(clippy::too_many_arguments)]
 pub async fn create_signed<T>(
     runtime_version: &RuntimeVersion,
     genesis_hash: T::Hash,
     nonce: T::Index,
+    era: Era,
+    checkpoint: T::Hash,
+    signed_extensions: &[String],
+    extensions: Option<&Arc<ExtensionRegistry<T>>>,
     call: Encoded,
     signer: &(dyn Signer<T> + Send + Sync),
 ) -> Result<UncheckedExtrinsic<T>, Error>
@@ -57,7 +92,15 @@ where
 {
     let spec_version = runtime_version.spec_version;
     let tx_version = runtime_version.transaction_version;
-    let extra: T::Extra = T::Extra::new(spec_version, tx_version, nonce, genesis_hash);
+    let mut extra: T::Extra = T::Extra::new(spec_version, tx_version, nonce, genesis_hash);
+    extra.set_era(era, checkpoint);
+    if let Some(extensions) = extensions {
+        extra.set_extension_registry(extensions.clone());
+    }
+    extra.set_signed_extensions(signed_extensions)?;
+    if let Some(tip) = signer.tip() {
+        extra.set_tip(tip);
+    }
     let payload = SignedPayload::<T>::new(call, extra.extra())?;
     let signed = signer.sign(payload).await?;
     Ok(signed)
@@ -0,0 +1,200 @@
+// Copyright 2019-2020 Parity Technologies (UK) Ltd.
+// This file is part of substrate-subxt.
+//
+// subxt is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// subxt is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with substrate-subxt.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Construct and sign extrinsics without a connection to a node.
+//!
+//! An online machine builds an [`UnsignedPayload`] and exports it as hex, an air-gapped machine
+//! imports and signs it, and the online machine decodes the signed extrinsic with
+//! [`extrinsic_from_hex`] and submits it with `Client::submit_extrinsic`.
+
+use codec::{Decode, Encode};
+use sp_runtime::{generic::Era, traits::SignedExtension};
+use std::sync::Arc;
+
+use super::{
+    ExtensionRegistry, Extra, SignedExtra, SignedPayload, Signer, Tip, UncheckedExtrinsic,
+};
+use crate::{runtimes::Runtime, Call, Encoded, Error, Metadata};
+
+/// Builds signing payloads from chain parameters supplied by the caller.
+#[derive(Clone, Debug)]
+pub struct OfflineBuilder<T: Runtime> {
+    metadata: Metadata,
+    genesis_hash: T::Hash,
+    spec_version: u32,
+    tx_version: u32,
+    era: Era,
+    checkpoint: T::Hash,
+    tip: Option<Tip<T>>,
+    extensions: Option<Arc<ExtensionRegistry<T>>>,
+}
+
+impl<T: Runtime> OfflineBuilder<T> {
+    /// Creates a new `OfflineBuilder` for immortal transactions without a tip.
+    pub fn new(
+        metadata: Metadata,
+        genesis_hash: T::Hash,
+        spec_version: u32,
+        tx_version: u32,
+    ) -> Self {
+        Self {
+            metadata,
+            genesis_hash,
+            spec_version,
+            tx_version,
+            era: Era::Immortal,
+            checkpoint: genesis_hash,
+            tip: None,
+            extensions: None,
+        }
+    }
+
+    /// Sets the era and the hash of the block it starts from.
+    pub fn set_era(mut self, era: Era, checkpoint: T::Hash) -> Self {
+        self.era = era;
+        self.checkpoint = checkpoint;
+        self
+    }
+
+    /// Sets the tip paid to the block author.
+    pub fn set_tip(mut self, tip: Tip<T>) -> Self {
+        self.tip = Some(tip);
+        self
+    }
+
+    /// Sets the registry the signed extensions of the runtime are built from.
+    pub fn set_extension_registry(mut self, registry: ExtensionRegistry<T>) -> Self {
+        self.extensions = Some(Arc::new(registry));
+        self
+    }
+
+    /// Returns the metadata calls are encoded with.
+    pub fn metadata(&self) -> &Metadata {
+        &self.metadata
+    }
+
+    /// Encodes a call using the cached metadata.
+    pub fn encode<C: Call<T>>(&self, call: C) -> Result<Encoded, Error> {
+        Ok(self
+            .metadata
+            .module_with_calls(C::MODULE)
+            .and_then(|module| module.call(C::FUNCTION, call))?)
+    }
+
+    /// Creates the payload to be signed for `call` by the account with `nonce`.
+    pub fn payload<C: Call<T>>(
+        &self,
+        call: C,
+        nonce: T::Index,
+    ) -> Result<UnsignedPayload<T>, Error> {
+        let call = self.encode(call)?;
+        let mut extra = T::Extra::new(self.spec_version, self.tx_version, nonce, self.genesis_hash);
+        extra.set_era(self.era, self.checkpoint);
+        if let Some(tip) = self.tip {
+            extra.set_tip(tip);
+        }
+        if let Some(extensions) = &self.extensions {
+            extra.set_extension_registry(extensions.clone());
+        }
+        extra.set_signed_extensions(self.metadata.signed_extensions())?;
+        Ok(UnsignedPayload { call, extra })
+    }
+}
+
+/// An encoded call with the extras it is signed with, ready to be carried to the signer.
+#[derive(Clone, Debug)]
+pub struct UnsignedPayload<T: Runtime> {
+    call: Encoded,
+    extra: T::Extra,
+}
+
+impl<T: Runtime> UnsignedPayload<T>
+where
+    <Extra<T> as SignedExtension>::AdditionalSigned: Send + Sync,
+{
+    /// Returns the encoded call.
+    pub fn call(&self) -> &Encoded {
+        &self.call
+    }
+
+    /// Returns the extras the payload is signed with.
+    pub fn extra(&self) -> &T::Extra {
+        &self.extra
+    }
+
+    /// Hex encodes the payload for transport.
+    pub fn to_hex(&self) -> String {
+        format!("0x{}", hex::encode((&self.call.0, &self.extra).encode()))
+    }
+
+    /// Decodes a payload exported with `to_hex`.
+    pub fn from_hex(payload: &str) -> Result<Self, Error> {
+        let bytes = decode_hex(payload)?;
+        let (call, extra) = <(Vec<u8>, T::Extra)>::decode(&mut &bytes[..])?;
+        Ok(Self {
+            call: Encoded(call),
+            extra,
+        })
+    }
+
+    /// Returns the bytes the signature is made over.
+    ///
+    /// Payloads longer than 256 bytes are hashed with blake2-256, so this is what an external
+    /// signer has to sign.
+    pub fn signing_payload(&self) -> Result<Vec<u8>, Error> {
+        let payload = SignedPayload::<T>::new(self.call.clone(), self.extra.extra())?;
+        Ok(payload.using_encoded(|payload| payload.to_vec()))
+    }
+
+    /// Signs the payload.
+    pub async fn sign(
+        self,
+        signer: &(dyn Signer<T> + Send + Sync),
+    ) -> Result<UncheckedExtrinsic<T>, Error> {
+        let payload = SignedPayload::<T>::new(self.call, self.extra.extra())?;
+        signer.sign(payload).await
+    }
+
+    /// Builds the extrinsic from a signature made over `signing_payload` elsewhere.
+    pub fn into_extrinsic(
+        self,
+        address: T::Address,
+        signature: T::Signature,
+    ) -> UncheckedExtrinsic<T> {
+        UncheckedExtrinsic::<T>::new_signed(self.call, address, signature, self.extra.extra())
+    }
+}
+
+/// Hex encodes a signed extrinsic.
+pub fn extrinsic_to_hex<T: Runtime>(extrinsic: &UncheckedExtrinsic<T>) -> String {
+    format!("0x{}", hex::encode(extrinsic.encode()))
+}
+
+/// Decodes a hex encoded extrinsic for submission.
+pub fn extrinsic_from_hex<T>(extrinsic: &str) -> Result<UncheckedExtrinsic<T>, Error>
+where
+    T: Runtime,
+    T::Signature: Decode,
+{
+    let bytes = decode_hex(extrinsic)?;
+    Ok(UncheckedExtrinsic::<T>::decode(&mut &bytes[..])?)
+}
+
+fn decode_hex(data: &str) -> Result<Vec<u8>, Error> {
+    let data = data.trim();
+    hex::decode(data.strip_prefix("0x").unwrap_or(data))
+        .map_err(|e| Error::Other(format!("Invalid hex: {}", e)))
+}
@@ -17,12 +17,24 @@
 //! A library to **sub**mit e**xt**rinsics to a
 //! [substrate](https://github.com/paritytech/substrate) node via RPC.
 
-use super::{SignedExtra, SignedPayload, UncheckedExtrinsic};
+use super::{SignedExtra, SignedPayload, Tip, UncheckedExtrinsic};
 use crate::runtimes::Runtime;
-use codec::Encode;
+use codec::{Decode, Encode};
+use futures::channel::oneshot;
 use sp_core::Pair;
 use sp_runtime::traits::{IdentifyAccount, SignedExtension, Verify};
-use std::{future::Future, pin::Pin};
+#[cfg(unix)]
+use std::os::unix::net::UnixStream;
+use std::{
+    future::Future,
+    io::{BufRead, BufReader, Read, Write},
+    net::{SocketAddr, TcpStream},
+    path::{Path, PathBuf},
+    pin::Pin,
+    process::{Child, Command, Stdio},
+    sync::{mpsc, Arc, Mutex},
+    time::{Duration, Instant},
+};
 
 /// Extrinsic signer.
 pub trait Signer<T: Runtime> {
@@ -32,6 +44,11 @@ pub trait Signer<T: Runtime> {
     /// Optionally returns a nonce.
     fn nonce(&self) -> Option<T::Index>;
 
+    /// Optionally returns a tip paid to the block author to prioritise the transaction.
+    fn tip(&self) -> Option<Tip<T>> {
+        None
+    }
+
     /// Takes an unsigned extrinsic and returns a signed extrinsic.
     ///
     /// Some signers may fail, for instance because the hardware on which the keys are located has
@@ -47,6 +64,7 @@ pub trait Signer<T: Runtime> {
 pub struct PairSigner<T: Runtime, P: Pair> {
     account_id: T::AccountId,
     nonce: Option<T::Index>,
+    tip: Option<Tip<T>>,
     signer: P,
 }
 
@@ -63,6 +81,7 @@ where
         Self {
             account_id,
             nonce: None,
+            tip: None,
             signer,
         }
     }
@@ -77,6 +96,11 @@ where
         self.nonce = self.nonce.map(|nonce| nonce + 1u32.into());
     }
 
+    /// Sets the tip paid with every transaction signed.
+    pub fn set_tip(&mut self, tip: Tip<T>) {
+        self.tip = Some(tip);
+    }
+
     /// Returns the signer.
     pub fn signer(&self) -> &P {
         &self.signer
@@ -99,6 +123,10 @@ where
         self.nonce
     }
 
+    fn tip(&self) -> Option<Tip<T>> {
+        self.tip
+    }
+
     fn sign(
         &self,
         extrinsic: SignedPayload<T>,
@@ -114,3 +142,345 @@ where
         Box::pin(async move { Ok(extrinsic) })
     }
 }
+
+/// Where a [`RemoteSigner`] sends payloads to be signed.
+///
+/// The payload is written as a single `0x` prefixed hex line and the endpoint answers with a
+/// single hex line holding the SCALE encoded signature of the runtime, e.g. a
+/// `MultiSignature`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum SigningEndpoint {
+    /// A Unix domain socket, one connection per payload.
+    #[cfg(unix)]
+    UnixSocket(PathBuf),
+    /// A TCP socket, one connection per payload.
+    Tcp(SocketAddr),
+    /// A command started for every payload, reading the payload on stdin and printing the
+    /// signature on stdout.
+    Command {
+        /// Program to run.
+        program: PathBuf,
+        /// Arguments passed to the program.
+        args: Vec<String>,
+    },
+}
+
+impl SigningEndpoint {
+    /// Sends `payload` to the endpoint and returns the signature bytes.
+    fn request(&self, payload: &[u8], timeout: Duration) -> Result<Vec<u8>, String> {
+        let request = format!("0x{}\n", hex::encode(payload));
+        let io_error = |e: std::io::Error| format!("Signing endpoint {:?}: {}", self, e);
+        let response = match self {
+            #[cfg(unix)]
+            SigningEndpoint::UnixSocket(path) => {
+                let mut stream = UnixStream::connect(path).map_err(io_error)?;
+                stream.set_read_timeout(Some(timeout)).map_err(io_error)?;
+                stream.set_write_timeout(Some(timeout)).map_err(io_error)?;
+                stream.write_all(request.as_bytes()).map_err(io_error)?;
+                read_line(stream).map_err(io_error)?
+            }
+            SigningEndpoint::Tcp(addr) => {
+                let mut stream = TcpStream::connect_timeout(addr, timeout).map_err(io_error)?;
+                stream.set_read_timeout(Some(timeout)).map_err(io_error)?;
+                stream.set_write_timeout(Some(timeout)).map_err(io_error)?;
+                stream.write_all(request.as_bytes()).map_err(io_error)?;
+                read_line(stream).map_err(io_error)?
+            }
+            SigningEndpoint::Command { program, args } => {
+                let child = Command::new(program)
+                    .args(args)
+                    .stdin(Stdio::piped())
+                    .stdout(Stdio::piped())
+                    .spawn()
+                    .map_err(io_error)?;
+                run_command(program, child, &request, timeout)?
+            }
+        };
+        let signature = response.trim();
+        let signature = signature.strip_prefix("0x").unwrap_or(signature);
+        hex::decode(signature).map_err(|e| format!("Invalid signature from {:?}: {}", self, e))
+    }
+}
+
+/// How long a signing endpoint may take to answer when no timeout is set.
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How often a running signing command is checked for exit.
+const COMMAND_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Writes `request` to a signing command and returns its output, killing the command if it is
+/// still running after `timeout`.
+fn run_command(
+    program: &Path,
+    mut child: Child,
+    request: &str,
+    timeout: Duration,
+) -> Result<String, String> {
+    let deadline = Instant::now() + timeout;
+    let fail = |child: &mut Child, err: String| {
+        let _ = child.kill();
+        let _ = child.wait();
+        Err(err)
+    };
+    if let Some(mut stdin) = child.stdin.take() {
+        // the request is a single short line, so this does not block on a full pipe
+        if let Err(e) = stdin.write_all(request.as_bytes()) {
+            return fail(&mut child, format!("Signing command {:?}: {}", program, e));
+        }
+    }
+    let status = loop {
+        match child.try_wait() {
+            Ok(Some(status)) => break status,
+            Ok(None) if Instant::now() < deadline => std::thread::sleep(COMMAND_POLL_INTERVAL),
+            Ok(None) => {
+                let err = format!(
+                    "Signing command {:?} timed out after {:?}",
+                    program, timeout
+                );
+                return fail(&mut child, err);
+            }
+            Err(e) => return fail(&mut child, format!("Signing command {:?}: {}", program, e)),
+        }
+    };
+    if !status.success() {
+        return Err(format!("Signing command {:?} failed: {}", program, status));
+    }
+    let mut output = Vec::new();
+    if let Some(mut stdout) = child.stdout.take() {
+        stdout
+            .read_to_end(&mut output)
+            .map_err(|e| format!("Signing command {:?}: {}", program, e))?;
+    }
+    String::from_utf8(output)
+        .map_err(|_| format!("Signing command {:?} returned invalid UTF-8", program))
+}
+
+/// A boxed blocking request run by a [`SigningWorker`].
+type SigningJob = Box<dyn FnOnce() + Send>;
+
+/// Runs the blocking endpoint requests of a [`RemoteSigner`] and its clones one after another
+/// on a single thread, started with the first request.
+#[derive(Default)]
+struct SigningWorker {
+    jobs: Mutex<Option<mpsc::Sender<SigningJob>>>,
+}
+
+impl SigningWorker {
+    /// Queues `job` on the worker thread, starting the thread if it is not running.
+    fn run(&self, job: SigningJob) -> Result<(), String> {
+        let mut jobs = self
+            .jobs
+            .lock()
+            .map_err(|_| "Signing worker poisoned".to_string())?;
+        if jobs.is_none() {
+            let (sender, receiver) = mpsc::channel::<SigningJob>();
+            std::thread::Builder::new()
+                .name("remote-signer".into())
+                .spawn(move || receiver.into_iter().for_each(|job| job()))
+                .map_err(|e| format!("Failed to start the signing worker: {}", e))?;
+            *jobs = Some(sender);
+        }
+        match jobs.as_ref().map(|sender| sender.send(job)) {
+            Some(Ok(())) => Ok(()),
+            _ => {
+                // the thread is gone, the next request starts a new one
+                *jobs = None;
+                Err("Signing worker stopped".into())
+            }
+        }
+    }
+}
+
+impl std::fmt::Debug for SigningWorker {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("SigningWorker").finish()
+    }
+}
+
+fn read_line<R: Read>(reader: R) -> std::io::Result<String> {
+    let mut line = String::new();
+    BufReader::new(reader).read_line(&mut line)?;
+    Ok(line)
+}
+
+/// Extrinsic signer delegating signing to an external process, e.g. a signing daemon holding
+/// the keys.
+///
+/// The signature returned by the endpoint is verified against the account id before the
+/// extrinsic is built. Requests of a signer and its clones are sent one at a time from a
+/// single background thread.
+#[derive(Clone, Debug)]
+pub struct RemoteSigner<T: Runtime> {
+    account_id: T::AccountId,
+    nonce: Option<T::Index>,
+    tip: Option<Tip<T>>,
+    endpoint: SigningEndpoint,
+    timeout: Duration,
+    worker: Arc<SigningWorker>,
+}
+
+impl<T: Runtime> RemoteSigner<T> {
+    /// Creates a new `Signer` for `account_id` signing through `endpoint`.
+    pub fn new(account_id: T::AccountId, endpoint: SigningEndpoint) -> Self {
+        Self {
+            account_id,
+            nonce: None,
+            tip: None,
+            endpoint,
+            timeout: DEFAULT_TIMEOUT,
+            worker: Default::default(),
+        }
+    }
+
+    /// Sets the nonce to a new value.
+    pub fn set_nonce(&mut self, nonce: T::Index) {
+        self.nonce = Some(nonce);
+    }
+
+    /// Increment the nonce.
+    pub fn increment_nonce(&mut self) {
+        self.nonce = self.nonce.map(|nonce| nonce + 1u32.into());
+    }
+
+    /// Sets the tip paid with every transaction signed.
+    pub fn set_tip(&mut self, tip: Tip<T>) {
+        self.tip = Some(tip);
+    }
+
+    /// Sets the connect, read and write timeout of socket endpoints and how long a signing
+    /// command may run before it is killed, 30 seconds by default.
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = timeout;
+    }
+
+    /// Returns the signing endpoint.
+    pub fn endpoint(&self) -> &SigningEndpoint {
+        &self.endpoint
+    }
+}
+
+impl<T> Signer<T> for RemoteSigner<T>
+where
+    T: Runtime,
+    T::AccountId: Into<T::Address> + 'static,
+    T::Signature: Decode,
+    <T::Signature as Verify>::Signer: IdentifyAccount<AccountId = T::AccountId>,
+    <<T::Extra as SignedExtra<T>>::Extra as SignedExtension>::AdditionalSigned: Send,
+{
+    fn account_id(&self) -> &T::AccountId {
+        &self.account_id
+    }
+
+    fn nonce(&self) -> Option<T::Index> {
+        self.nonce
+    }
+
+    fn tip(&self) -> Option<Tip<T>> {
+        self.tip
+    }
+
+    fn sign(
+        &self,
+        extrinsic: SignedPayload<T>,
+    ) -> Pin<Box<dyn Future<Output = Result<UncheckedExtrinsic<T>, String>> + Send>> {
+        // `using_encoded` hashes payloads longer than 256 bytes, like the runtime does
+        let payload = extrinsic.using_encoded(|payload| payload.to_vec());
+        let (call, extra, _) = extrinsic.deconstruct();
+        let account_id = self.account_id.clone();
+        let endpoint = self.endpoint.clone();
+        let timeout = self.timeout;
+        // the endpoints block, so they run on the worker thread rather than the executor
+        let (sender, receiver) = oneshot::channel();
+        let request = payload.clone();
+        let queued = self.worker.run(Box::new(move || {
+            let _ = sender.send(endpoint.request(&request, timeout));
+        }));
+        Box::pin(async move {
+            queued?;
+            let bytes = receiver
+                .await
+                .map_err(|_| "Signing worker stopped".to_string())??;
+            let signature = T::Signature::decode(&mut &bytes[..])
+                .map_err(|e| format!("Invalid signature encoding: {}", e))?;
+            if !signature.verify(&payload[..], &account_id) {
+                return Err("Remote signature does not match the account".into());
+            }
+            Ok(UncheckedExtrinsic::<T>::new_signed(
+                call,
+                account_id.into(),
+                signature,
+                extra,
+            ))
+        })
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    fn command(script: &str) -> SigningEndpoint {
+        SigningEndpoint::Command {
+            program: "sh".into(),
+            args: vec!["-c".into(), script.into()],
+        }
+    }
+
+    #[test]
+    fn test_command_signs() {
+        // the stub answers with the payload it read
+        let endpoint = command("read payload; echo $payload");
+        let signature = endpoint.request(&[1, 2, 3], DEFAULT_TIMEOUT).unwrap();
+        assert_eq!(signature, vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn test_command_fails() {
+        let endpoint = command("read payload; exit 3");
+        let err = endpoint.request(&[1, 2, 3], DEFAULT_TIMEOUT).unwrap_err();
+        assert!(err.contains("failed"), "{}", err);
+
+        let endpoint = command("read payload; echo not-hex");
+        assert!(endpoint.request(&[1, 2, 3], DEFAULT_TIMEOUT).is_err());
+    }
+
+    #[test]
+    fn test_command_times_out() {
+        let endpoint = command("sleep 10");
+        let started = Instant::now();
+        let err = endpoint
+            .request(&[1, 2, 3], Duration::from_millis(200))
+            .unwrap_err();
+        assert!(err.contains("timed out"), "{}", err);
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn test_tcp_times_out() {
+        // the connection is queued by the listener but never answered
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let endpoint = SigningEndpoint::Tcp(listener.local_addr().unwrap());
+        let started = Instant::now();
+        assert!(endpoint
+            .request(&[1, 2, 3], Duration::from_millis(200))
+            .is_err());
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn test_worker_reuses_its_thread() {
+        let worker = SigningWorker::default();
+        let (sender, receiver) = mpsc::channel();
+        for _ in 0..3 {
+            let sender = sender.clone();
+            worker
+                .run(Box::new(move || {
+                    sender.send(std::thread::current().id()).unwrap();
+                }))
+                .unwrap();
+        }
+        let threads: Vec<_> = receiver.iter().take(3).collect();
+        assert!(threads.iter().all(|thread| *thread == threads[0]));
+        assert_ne!(threads[0], std::thread::current().id());
+    }
+}
@@ -25,7 +25,10 @@ use std::fmt::Debug;
 
 /// The subset of the `pallet_balances::Trait` that a client must implement.
 #[module]
+#[rustfmt::skip]
 pub trait Balances: System {
+    #![event_alias(Status = BalanceStatus)]
+
     /// The balance of an account.
     type Balance: Parameter
         + Member
@@ -111,6 +114,21 @@ pub enum Reasons {
     All,
 }
 
+/// Status of funds.
+#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum BalanceStatus {
+    /// Funds are free, as corresponding to `free` item in Balances.
+    Free,
+    /// Funds are reserved, as corresponding to `reserved` item in Balances.
+    Reserved,
+}
+
+impl Default for BalanceStatus {
+    fn default() -> Self {
+        BalanceStatus::Free
+    }
+}
+
 /// Transfer some liquid free balance to another account.
 ///
 /// `transfer` will set the `FreeBalance` of the sender and receiver.
@@ -136,3 +154,116 @@ pub struct TransferEvent<T: Balances> {
     /// Amount of balance that was transfered.
     pub amount: T::Balance,
 }
+
+/// Same as the `transfer` call, but with a check that the transfer will not kill the
+/// origin account.
+#[derive(Clone, Debug, PartialEq, Call, Encode)]
+pub struct TransferKeepAliveCall<'a, T: Balances> {
+    /// Destination of the transfer.
+    pub to: &'a <T as System>::Address,
+    /// Amount to transfer.
+    #[codec(compact)]
+    pub amount: T::Balance,
+}
+
+/// Exactly as `transfer`, except the origin must be root and the source account may be
+/// specified.
+///
+/// Must be wrapped in a `SudoCall` to be dispatched.
+#[derive(Clone, Debug, PartialEq, Call, Encode)]
+pub struct ForceTransferCall<'a, T: Balances> {
+    /// Account balance is transferred from.
+    pub source: &'a <T as System>::Address,
+    /// Destination of the transfer.
+    pub dest: &'a <T as System>::Address,
+    /// Amount to transfer.
+    #[codec(compact)]
+    pub value: T::Balance,
+}
+
+/// Set the balances of a given account.
+///
+/// This will alter `FreeBalance` and `ReservedBalance` in storage. If the new free or
+/// reserved balance is below the existential deposit, it will reset the account.
+/// The origin must be root, so the call must be wrapped in a `SudoCall`.
+#[derive(Clone, Debug, PartialEq, Call, Encode)]
+pub struct SetBalanceCall<'a, T: Balances> {
+    /// Account whose balance is set.
+    pub who: &'a <T as System>::Address,
+    /// New free balance.
+    #[codec(compact)]
+    pub new_free: T::Balance,
+    /// New reserved balance.
+    #[codec(compact)]
+    pub new_reserved: T::Balance,
+}
+
+/// An account was created with some free balance.
+#[derive(Clone, Debug, Eq, PartialEq, Event, Decode)]
+pub struct EndowedEvent<T: Balances> {
+    /// Account that was created.
+    pub account: <T as System>::AccountId,
+    /// Free balance the account was created with.
+    pub free_balance: T::Balance,
+}
+
+/// An account was removed whose balance was non-zero but below the existential deposit,
+/// resulting in an outright loss.
+#[derive(Clone, Debug, Eq, PartialEq, Event, Decode)]
+pub struct DustLostEvent<T: Balances> {
+    /// Account that was removed.
+    pub account: <T as System>::AccountId,
+    /// Balance that was lost.
+    pub balance: T::Balance,
+}
+
+/// A balance was set by root.
+#[derive(Clone, Debug, Eq, PartialEq, Event, Decode)]
+pub struct BalanceSetEvent<T: Balances> {
+    /// Account whose balance was set.
+    pub who: <T as System>::AccountId,
+    /// New free balance.
+    pub free: T::Balance,
+    /// New reserved balance.
+    pub reserved: T::Balance,
+}
+
+/// Some amount was deposited (e.g. for transaction fees).
+#[derive(Clone, Debug, Eq, PartialEq, Event, Decode)]
+pub struct DepositEvent<T: Balances> {
+    /// Account the amount was deposited into.
+    pub who: <T as System>::AccountId,
+    /// Amount deposited.
+    pub amount: T::Balance,
+}
+
+/// Some balance was reserved (moved from free to reserved).
+#[derive(Clone, Debug, Eq, PartialEq, Event, Decode)]
+pub struct ReservedEvent<T: Balances> {
+    /// Account whose balance was reserved.
+    pub who: <T as System>::AccountId,
+    /// Amount reserved.
+    pub amount: T::Balance,
+}
+
+/// Some balance was unreserved (moved from reserved to free).
+#[derive(Clone, Debug, Eq, PartialEq, Event, Decode)]
+pub struct UnreservedEvent<T: Balances> {
+    /// Account whose balance was unreserved.
+    pub who: <T as System>::AccountId,
+    /// Amount unreserved.
+    pub amount: T::Balance,
+}
+
+/// Some balance was moved from the reserve of the first account to the second account.
+#[derive(Clone, Debug, Eq, PartialEq, Event, Decode)]
+pub struct ReserveRepatriatedEvent<T: Balances> {
+    /// Account the reserved balance was moved from.
+    pub from: <T as System>::AccountId,
+    /// Account the balance was moved to.
+    pub to: <T as System>::AccountId,
+    /// Amount moved.
+    pub amount: T::Balance,
+    /// Whether the balance ended up free or reserved at the destination.
+    pub destination_status: BalanceStatus,
+}
@@ -29,6 +29,7 @@ pub mod session;
 pub mod staking;
 pub mod sudo;
 pub mod system;
+pub mod utility;
 
 /// Store trait.
 pub trait Store<T>: Encode {
@@ -0,0 +1,70 @@
+// Copyright 2019-2020 Parity Technologies (UK) Ltd.
+// This file is part of substrate-subxt.
+//
+// subxt is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// subxt is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with substrate-subxt.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Implements support for the pallet_utility module.
+
+use crate::{
+    frame::system::{System, SystemEventsDecoder},
+    Encoded,
+};
+use codec::{Decode, Encode};
+use core::marker::PhantomData;
+use sp_runtime::DispatchError;
+
+/// The subset of the `pallet_utility::Trait` that a client must implement.
+#[module]
+pub trait Utility: System {}
+
+/// Send a batch of dispatch calls.
+///
+/// Calls are dispatched one after the other and the batch stops at the first failing call,
+/// without reverting the calls that already succeeded.
+#[derive(Clone, Debug, Eq, PartialEq, Call, Encode)]
+pub struct BatchCall<'a, T: Utility> {
+    /// Runtime marker.
+    pub _runtime: PhantomData<T>,
+    /// Encoded calls to dispatch.
+    pub calls: &'a [Encoded],
+}
+
+/// Send a batch of dispatch calls and atomically execute them.
+///
+/// The whole transaction will rollback and fail if any of the calls failed.
+#[derive(Clone, Debug, Eq, PartialEq, Call, Encode)]
+pub struct BatchAllCall<'a, T: Utility> {
+    /// Runtime marker.
+    pub _runtime: PhantomData<T>,
+    /// Encoded calls to dispatch.
+    pub calls: &'a [Encoded],
+}
+
+/// Batch of dispatches did not complete fully.
+#[derive(Clone, Debug, Eq, PartialEq, Event, Decode)]
+pub struct BatchInterruptedEvent<T: Utility> {
+    /// Runtime marker.
+    pub _runtime: PhantomData<T>,
+    /// Index of the first failing dispatch.
+    pub index: u32,
+    /// Error of the failing dispatch.
+    pub error: DispatchError,
+}
+
+/// Batch of dispatches completed fully with no error.
+#[derive(Clone, Debug, Eq, PartialEq, Event, Decode)]
+pub struct BatchCompletedEvent<T: Utility> {
+    /// Runtime marker.
+    pub _runtime: PhantomData<T>,
+}
@@ -51,37 +51,52 @@ pub use sp_runtime;
 
 use codec::Decode;
 use futures::future;
-use jsonrpsee::client::Subscription;
+pub use jsonrpsee::client::Subscription;
 use sp_core::{
     storage::{StorageChangeSet, StorageData, StorageKey},
     Bytes,
 };
 pub use sp_runtime::traits::SignedExtension;
+use sp_runtime::{
+    generic::Era,
+    traits::{Header, UniqueSaturatedInto, Zero},
+};
 pub use sp_version::RuntimeVersion;
-use std::marker::PhantomData;
+use std::{marker::PhantomData, sync::Arc, time::Duration};
 
 mod error;
 mod events;
 pub mod extrinsic;
 mod frame;
 mod metadata;
+mod nonce;
 mod rpc;
 mod runtimes;
 mod subscription;
 
 pub use crate::{
-    error::Error,
-    events::{EventsDecoder, RawEvent},
-    extrinsic::{PairSigner, SignedExtra, Signer, UncheckedExtrinsic},
+    error::{Error, ModuleError, RuntimeError, TransactionError},
+    events::{BlockEvents, EventsDecoder, Raw, RawEvent},
+    extrinsic::{
+        ExtensionRegistry, Mortality, OfflineBuilder, PairSigner, RemoteSigner, SignedExtra,
+        Signer, SigningEndpoint, UncheckedExtrinsic, UnsignedPayload,
+    },
     frame::*,
     indra_proc_macro::*,
     metadata::{Metadata, MetadataError},
-    rpc::{BlockNumber, ExtrinsicSuccess, ReadProof, SystemProperties},
+    nonce::{NonceManager, NonceReservation},
+    rpc::{
+        events_key, BlockNumber, ExtrinsicSuccess, ReadProof, RuntimeDispatchInfo,
+        SystemProperties, TransactionProgress, TransactionStatus,
+    },
     runtimes::*,
     subscription::*,
 };
 use crate::{
-    frame::system::{AccountStoreExt, Phase, System},
+    frame::{
+        balances::Balances,
+        system::{Phase, System},
+    },
     rpc::{ChainBlock, Rpc},
 };
 
@@ -92,6 +107,9 @@ pub struct ClientBuilder<T: Runtime> {
     url: Option<String>,
     client: Option<jsonrpsee::Client>,
     page_size: Option<u32>,
+    mortality: Option<Mortality>,
+    events_timeout: Option<Duration>,
+    extensions: Option<ExtensionRegistry<T>>,
 }
 
 impl<T: Runtime> ClientBuilder<T> {
@@ -102,6 +120,9 @@ impl<T: Runtime> ClientBuilder<T> {
             url: None,
             client: None,
             page_size: None,
+            mortality: None,
+            events_timeout: None,
+            extensions: None,
         }
     }
 
@@ -123,6 +144,27 @@ impl<T: Runtime> ClientBuilder<T> {
         self
     }
 
+    /// Set the lifetime of signed transactions, mortal for 64 blocks from the finalized block
+    /// by default.
+    pub fn set_mortality(mut self, mortality: Mortality) -> Self {
+        self.mortality = Some(mortality);
+        self
+    }
+
+    /// Set how long to wait for the events of an extrinsic once it is in a block, 30 seconds
+    /// by default.
+    pub fn set_events_timeout(mut self, timeout: Duration) -> Self {
+        self.events_timeout = Some(timeout);
+        self
+    }
+
+    /// Set the registry the signed extensions of the runtime are built from, for extras that
+    /// follow the metadata such as `DynamicExtra`.
+    pub fn set_extension_registry(mut self, registry: ExtensionRegistry<T>) -> Self {
+        self.extensions = Some(registry);
+        self
+    }
+
     /// Creates a new Client.
     pub async fn build(self) -> Result<Client<T>, Error> {
         let client = if let Some(client) = self.client {
@@ -135,7 +177,10 @@ impl<T: Runtime> ClientBuilder<T> {
                 jsonrpsee::http_client(url)
             }
         };
-        let rpc = Rpc::new(client);
+        let mut rpc = Rpc::new(client);
+        if let Some(timeout) = self.events_timeout {
+            rpc.set_events_timeout(timeout);
+        }
         let (metadata, genesis_hash, runtime_version, properties) = future::join4(
             rpc.metadata(),
             rpc.genesis_hash(),
@@ -143,14 +188,32 @@ impl<T: Runtime> ClientBuilder<T> {
             rpc.system_properties(),
         )
         .await;
+        let (metadata, genesis_hash, runtime_version) =
+            (metadata?, genesis_hash?, runtime_version?);
+        let mut extra = T::Extra::new(
+            runtime_version.spec_version,
+            runtime_version.transaction_version,
+            Default::default(),
+            genesis_hash,
+        );
+        let extensions = self.extensions.map(Arc::new);
+        if let Some(extensions) = &extensions {
+            extra.set_extension_registry(extensions.clone());
+        }
+        extra.set_signed_extensions(metadata.signed_extensions())?;
+        metadata.check_signed_extensions(&extra.identifiers())?;
+        let nonces = NonceManager::new(rpc.clone());
         Ok(Client {
             rpc,
-            genesis_hash: genesis_hash?,
-            metadata: metadata?,
+            genesis_hash,
+            metadata,
             properties: properties.unwrap_or_else(|_| Default::default()),
-            runtime_version: runtime_version?,
+            runtime_version,
             _marker: PhantomData,
             page_size: self.page_size.unwrap_or(10),
+            mortality: self.mortality.unwrap_or_default(),
+            nonces,
+            extensions,
         })
     }
 }
@@ -164,6 +227,9 @@ pub struct Client<T: Runtime> {
     runtime_version: RuntimeVersion,
     _marker: PhantomData<(fn() -> T::Signature, T::Extra)>,
     page_size: u32,
+    mortality: Mortality,
+    nonces: NonceManager<T>,
+    extensions: Option<Arc<ExtensionRegistry<T>>>,
 }
 
 impl<T: Runtime> Clone for Client<T> {
@@ -176,6 +242,9 @@ impl<T: Runtime> Clone for Client<T> {
             runtime_version: self.runtime_version.clone(),
             _marker: PhantomData,
             page_size: self.page_size,
+            mortality: self.mortality,
+            nonces: self.nonces.clone(),
+            extensions: self.extensions.clone(),
         }
     }
 }
@@ -224,6 +293,38 @@ impl<T: Runtime, F: Store<T>> KeyIter<T, F> {
             }
         }
     }
+
+    /// Returns the next entry of a map, with the map key decoded from the storage key.
+    pub async fn next_map<K: Decode>(&mut self) -> Result<Option<(K, F::Returns)>, Error> {
+        if let Some((key, value)) = self.next().await? {
+            let key = self
+                .client
+                .metadata
+                .module(F::MODULE)?
+                .storage(F::FIELD)?
+                .decode_map_key(&key)?;
+            Ok(Some((key, value)))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Returns the next entry of a double map, with both map keys decoded from the storage key.
+    pub async fn next_double_map<K1: Decode, K2: Decode>(
+        &mut self,
+    ) -> Result<Option<((K1, K2), F::Returns)>, Error> {
+        if let Some((key, value)) = self.next().await? {
+            let keys = self
+                .client
+                .metadata
+                .module(F::MODULE)?
+                .storage(F::FIELD)?
+                .decode_double_map_key(&key)?;
+            Ok(Some((keys, value)))
+        } else {
+            Ok(None)
+        }
+    }
 }
 
 impl<T: Runtime> Client<T> {
@@ -237,6 +338,33 @@ impl<T: Runtime> Client<T> {
         &self.metadata
     }
 
+    /// Returns the lifetime of signed transactions.
+    pub fn mortality(&self) -> Mortality {
+        self.mortality
+    }
+
+    /// Returns the era for a transaction signed now and the hash of the block it starts from.
+    ///
+    /// Extrinsics signed together can share it through [`Client::create_signed_with_era`].
+    pub async fn era(&self) -> Result<(Era, T::Hash), Error> {
+        let (header, period) = match self.mortality {
+            Mortality::Immortal => return Ok((Era::Immortal, self.genesis_hash)),
+            Mortality::Best(period) => (self.header::<T::Hash>(None).await?, period),
+            Mortality::Finalized(period) => {
+                let hash = self.finalized_head().await?;
+                (self.header(Some(hash)).await?, period)
+            }
+        };
+        let header = header.ok_or("Header of the checkpoint block not found")?;
+        let number: u64 = (*header.number()).unique_saturated_into();
+        Ok((Era::mortal(period, number), header.hash()))
+    }
+
+    /// Fetches the SCALE encoded metadata, to be cached and loaded with `Metadata::from_bytes`.
+    pub async fn metadata_bytes(&self) -> Result<Bytes, Error> {
+        self.rpc.metadata_bytes().await
+    }
+
     /// Returns the system properties
     pub fn properties(&self) -> &SystemProperties {
         &self.properties
@@ -324,6 +452,15 @@ impl<T: Runtime> Client<T> {
         self.rpc.query_storage(keys, from, to).await
     }
 
+    /// Query storage entries at a single block
+    pub async fn query_storage_at(
+        &self,
+        keys: &[StorageKey],
+        at: Option<T::Hash>,
+    ) -> Result<Vec<StorageChangeSet<<T as System>::Hash>>, Error> {
+        self.rpc.query_storage_at(keys, at).await
+    }
+
     /// Get a header
     pub async fn header<H>(&self, hash: Option<H>) -> Result<Option<T::Header>, Error>
     where
@@ -406,6 +543,10 @@ impl<T: Runtime> Client<T> {
     }
 
     /// Creates a signed extrinsic.
+    ///
+    /// The nonce and tip of the signer are used when set, see `PairSigner::set_tip`. Without a
+    /// nonce the next index of the account is used without reserving it, extrinsics signed
+    /// concurrently for an account should come from [`Client::create_signed_reserved`].
     pub async fn create_signed<C: Call<T> + Send + Sync>(
         &self,
         call: C,
@@ -414,16 +555,89 @@ impl<T: Runtime> Client<T> {
     where
         <<T::Extra as SignedExtra<T>>::Extra as SignedExtension>::AdditionalSigned: Send + Sync,
     {
-        let account_nonce = if let Some(nonce) = signer.nonce() {
-            nonce
-        } else {
-            self.account(signer.account_id(), None).await?.nonce
+        let nonce = self.peek_nonce(signer).await?;
+        self.create_signed_with_nonce(call, signer, nonce).await
+    }
+
+    /// Creates a signed extrinsic, reserving the next nonce of the signer's account from the
+    /// client's `NonceManager` unless the signer has a nonce.
+    ///
+    /// The reservation is released when dropped unless it is settled with the outcome of
+    /// submitting the extrinsic or tracked by its `TransactionProgress`.
+    pub async fn create_signed_reserved<C: Call<T> + Send + Sync>(
+        &self,
+        call: C,
+        signer: &(dyn Signer<T> + Send + Sync),
+    ) -> Result<(UncheckedExtrinsic<T>, Option<NonceReservation<T>>), Error>
+    where
+        <<T::Extra as SignedExtra<T>>::Extra as SignedExtension>::AdditionalSigned: Send + Sync,
+    {
+        let (era, checkpoint) = self.era().await?;
+        self.create_signed_with_era(call, signer, era, checkpoint)
+            .await
+    }
+
+    /// Creates a signed extrinsic like [`Client::create_signed_reserved`], valid for `era`
+    /// starting at the block with hash `checkpoint` as returned by [`Client::era`].
+    ///
+    /// Extrinsics signed in a burst share one era instead of fetching the checkpoint each.
+    pub async fn create_signed_with_era<C: Call<T> + Send + Sync>(
+        &self,
+        call: C,
+        signer: &(dyn Signer<T> + Send + Sync),
+        era: Era,
+        checkpoint: T::Hash,
+    ) -> Result<(UncheckedExtrinsic<T>, Option<NonceReservation<T>>), Error>
+    where
+        <<T::Extra as SignedExtra<T>>::Extra as SignedExtension>::AdditionalSigned: Send + Sync,
+    {
+        let reservation = match signer.nonce() {
+            Some(_) => None,
+            None => Some(self.nonces.reserve(signer.account_id()).await?),
         };
+        let nonce = match &reservation {
+            Some(reservation) => reservation.nonce(),
+            None => self.peek_nonce(signer).await?,
+        };
+        let extrinsic = self.sign(call, signer, nonce, era, checkpoint).await?;
+        Ok((extrinsic, reservation))
+    }
+
+    /// Creates a signed extrinsic with `nonce`, ignoring the nonce of the signer.
+    pub async fn create_signed_with_nonce<C: Call<T> + Send + Sync>(
+        &self,
+        call: C,
+        signer: &(dyn Signer<T> + Send + Sync),
+        nonce: T::Index,
+    ) -> Result<UncheckedExtrinsic<T>, Error>
+    where
+        <<T::Extra as SignedExtra<T>>::Extra as SignedExtension>::AdditionalSigned: Send + Sync,
+    {
+        let (era, checkpoint) = self.era().await?;
+        self.sign(call, signer, nonce, era, checkpoint).await
+    }
+
+    /// Signs `call` with `nonce`, valid for `era` starting at the block with hash `checkpoint`.
+    async fn sign<C: Call<T> + Send + Sync>(
+        &self,
+        call: C,
+        signer: &(dyn Signer<T> + Send + Sync),
+        nonce: T::Index,
+        era: Era,
+        checkpoint: T::Hash,
+    ) -> Result<UncheckedExtrinsic<T>, Error>
+    where
+        <<T::Extra as SignedExtra<T>>::Extra as SignedExtension>::AdditionalSigned: Send + Sync,
+    {
         let call = self.encode(call)?;
         let signed = extrinsic::create_signed(
             &self.runtime_version,
             self.genesis_hash,
-            account_nonce,
+            nonce,
+            era,
+            checkpoint,
+            self.metadata.signed_extensions(),
+            self.extensions.as_ref(),
             call,
             signer,
         )
@@ -431,10 +645,66 @@ impl<T: Runtime> Client<T> {
         Ok(signed)
     }
 
+    /// Returns the nonces reserved for signers without a nonce.
+    pub fn nonces(&self) -> &NonceManager<T> {
+        &self.nonces
+    }
+
+    /// Returns the nonce of the signer or the next nonce of its account, without reserving it.
+    async fn peek_nonce(&self, signer: &(dyn Signer<T> + Send + Sync)) -> Result<T::Index, Error> {
+        match signer.nonce() {
+            Some(nonce) => Ok(nonce),
+            None => self.account_next_index(signer.account_id()).await,
+        }
+    }
+
+    /// Fetch every event of a block, including those emitted while initializing and
+    /// finalizing it.
+    ///
+    /// The events are decoded with the metadata of the runtime that executed the block, which
+    /// is fetched again for a block from before a runtime upgrade. Type sizes come from
+    /// `Runtime::register_type_sizes`.
+    pub async fn events_at(&self, block_hash: T::Hash) -> Result<BlockEvents<T>, Error> {
+        let header = self
+            .header(Some(block_hash))
+            .await?
+            .ok_or_else(|| Error::Other(format!("Failed to find block {:?}", block_hash)))?;
+        // a block is executed by the runtime in the state of its parent
+        let runtime_at = if header.number().is_zero() {
+            block_hash
+        } else {
+            *header.parent_hash()
+        };
+        let version = self.rpc.runtime_version(Some(runtime_at)).await?;
+        let metadata = if version.spec_version == self.runtime_version.spec_version {
+            self.metadata.clone()
+        } else {
+            self.rpc.metadata_at(Some(runtime_at)).await?
+        };
+        let mut decoder = EventsDecoder::new(metadata);
+        T::register_type_sizes(&mut decoder);
+        self.rpc.events_at(block_hash, &decoder).await
+    }
+
+    /// Returns the index of the extrinsic with hash `ext_hash` in a block.
+    pub async fn extrinsic_index(
+        &self,
+        block_hash: T::Hash,
+        ext_hash: T::Hash,
+    ) -> Result<usize, Error> {
+        self.rpc.extrinsic_index(block_hash, ext_hash).await
+    }
+
+    /// Returns an events decoder for the runtime.
+    pub fn runtime_events_decoder(&self) -> EventsDecoder<T> {
+        let mut decoder = EventsDecoder::new(self.metadata().clone());
+        T::register_type_sizes(&mut decoder);
+        decoder
+    }
+
     /// Returns an events decoder for a call.
     pub fn events_decoder<C: Call<T>>(&self) -> EventsDecoder<T> {
-        let metadata = self.metadata().clone();
-        let mut decoder = EventsDecoder::new(metadata);
+        let mut decoder = self.runtime_events_decoder();
         C::events_decoder(&mut decoder);
         decoder
     }
@@ -447,6 +717,14 @@ impl<T: Runtime> Client<T> {
         self.rpc.submit_extrinsic(extrinsic).await
     }
 
+    /// Submit an extrinsic and subscribe to its status in the transaction pool
+    pub async fn watch_extrinsic(
+        &self,
+        extrinsic: UncheckedExtrinsic<T>,
+    ) -> Result<Subscription<TransactionStatus<T::Hash, T::Hash>>, Error> {
+        self.rpc.watch_extrinsic(extrinsic).await
+    }
+
     /// Create and submit an extrinsic and return corresponding Event if successful
     pub async fn submit_and_watch_extrinsic(
         &self,
@@ -458,6 +736,27 @@ impl<T: Runtime> Client<T> {
             .await
     }
 
+    /// Submit an extrinsic and follow every status of it, see [`TransactionProgress`].
+    pub async fn watch_progress(
+        &self,
+        extrinsic: UncheckedExtrinsic<T>,
+        decoder: EventsDecoder<T>,
+    ) -> Result<TransactionProgress<T>, Error> {
+        self.rpc.watch_progress(extrinsic, decoder).await
+    }
+
+    /// Submit an extrinsic and follow every status of it, subscribing to the events of every
+    /// block so those of its block arrive without another request.
+    pub async fn watch_progress_with_events(
+        &self,
+        extrinsic: UncheckedExtrinsic<T>,
+        decoder: EventsDecoder<T>,
+    ) -> Result<TransactionProgress<T>, Error> {
+        self.rpc
+            .watch_progress_with_events(extrinsic, decoder)
+            .await
+    }
+
     /// Submits a transaction to the chain.
     pub async fn submit<C: Call<T> + Send + Sync>(
         &self,
@@ -467,8 +766,12 @@ impl<T: Runtime> Client<T> {
     where
         <<T::Extra as SignedExtra<T>>::Extra as SignedExtension>::AdditionalSigned: Send + Sync,
     {
-        let extrinsic = self.create_signed(call, signer).await?;
-        self.submit_extrinsic(extrinsic).await
+        let (extrinsic, reservation) = self.create_signed_reserved(call, signer).await?;
+        let result = self.submit_extrinsic(extrinsic).await;
+        if let Some(reservation) = reservation {
+            reservation.settle(&result);
+        }
+        result
     }
 
     /// Submits transaction to the chain and watch for events.
@@ -480,9 +783,88 @@ impl<T: Runtime> Client<T> {
     where
         <<T::Extra as SignedExtra<T>>::Extra as SignedExtension>::AdditionalSigned: Send + Sync,
     {
-        let extrinsic = self.create_signed(call, signer).await?;
+        let (extrinsic, reservation) = self.create_signed_reserved(call, signer).await?;
         let decoder = self.events_decoder::<C>();
-        self.submit_and_watch_extrinsic(extrinsic, decoder).await
+        let progress = self.watch_progress_with_events(extrinsic, decoder).await;
+        let progress = match (progress, reservation) {
+            (Ok(progress), Some(reservation)) => progress.track_nonce(reservation),
+            (Ok(progress), None) => progress,
+            (Err(err), reservation) => {
+                if let Some(reservation) = reservation {
+                    reservation.reject(&err);
+                }
+                return Err(err);
+            }
+        };
+        progress.wait_for_in_block().await
+    }
+
+    /// Query the weight, class and partial fee of an extrinsic.
+    pub async fn query_info(
+        &self,
+        extrinsic: &UncheckedExtrinsic<T>,
+        at: Option<T::Hash>,
+    ) -> Result<RuntimeDispatchInfo<T::Balance>, Error>
+    where
+        T: Balances,
+    {
+        self.rpc.query_info(extrinsic, at).await
+    }
+
+    /// Estimates the weight, class and partial fee of `call` signed by `signer`.
+    ///
+    /// The signature is not checked, so a throwaway signer of the same scheme gives the same
+    /// estimate. The nonce of the signer is used when set, otherwise the next nonce of its
+    /// account without reserving it. The tip of the signer is not part of the partial fee.
+    pub async fn estimate_fee<C: Call<T> + Send + Sync>(
+        &self,
+        call: C,
+        signer: &(dyn Signer<T> + Send + Sync),
+    ) -> Result<RuntimeDispatchInfo<T::Balance>, Error>
+    where
+        T: Balances,
+        <<T::Extra as SignedExtra<T>>::Extra as SignedExtension>::AdditionalSigned: Send + Sync,
+    {
+        let nonce = self.peek_nonce(signer).await?;
+        let extrinsic = self.create_signed_with_nonce(call, signer, nonce).await?;
+        self.query_info(&extrinsic, None).await
+    }
+
+    /// Dry runs `call` signed by `signer` at a block, the best block by default.
+    ///
+    /// The nonce of the signer is used when set, otherwise the next nonce of its account without
+    /// reserving it. An invalid transaction fails with `Error::Invalid` and a failed dispatch
+    /// with the `Error::Runtime` named in the metadata.
+    pub async fn dry_run<C: Call<T> + Send + Sync>(
+        &self,
+        call: C,
+        signer: &(dyn Signer<T> + Send + Sync),
+        at: Option<T::Hash>,
+    ) -> Result<(), Error>
+    where
+        <<T::Extra as SignedExtra<T>>::Extra as SignedExtension>::AdditionalSigned: Send + Sync,
+    {
+        let nonce = self.peek_nonce(signer).await?;
+        let extrinsic = self.create_signed_with_nonce(call, signer, nonce).await?;
+        self.dry_run_extrinsic(&extrinsic, at).await
+    }
+
+    /// Dry runs a signed extrinsic at a block, the best block by default.
+    pub async fn dry_run_extrinsic(
+        &self,
+        extrinsic: &UncheckedExtrinsic<T>,
+        at: Option<T::Hash>,
+    ) -> Result<(), Error> {
+        match self.rpc.dry_run(extrinsic, at).await? {
+            Ok(Ok(())) => Ok(()),
+            Ok(Err(error)) => Err(RuntimeError::from_dispatch(self.metadata(), error)?.into()),
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    /// Fetch the next nonce of an account, counting its transactions in the pool.
+    pub async fn account_next_index(&self, account: &T::AccountId) -> Result<T::Index, Error> {
+        self.rpc.account_next_index(account).await
     }
 
     /// Insert a key into the keystore.
@@ -527,3 +909,16 @@ impl codec::Encode for Encoded {
         self.0.to_owned()
     }
 }
+
+/// Consumes the remainder of the input, so `Encoded` can only be decoded as the last field of a
+/// type, like the call of an extrinsic.
+impl codec::Decode for Encoded {
+    fn decode<I: codec::Input>(input: &mut I) -> Result<Self, codec::Error> {
+        let len = input
+            .remaining_len()?
+            .ok_or("Cannot decode raw bytes from an input of unknown length")?;
+        let mut bytes = vec![0u8; len];
+        input.read(&mut bytes)?;
+        Ok(Encoded(bytes))
+    }
+}
@@ -24,7 +24,7 @@ use frame_metadata::{
 };
 use sp_core::storage::StorageKey;
 
-use crate::Encoded;
+use crate::{Encoded, Error};
 
 /// Metadata error.
 #[derive(Debug, thiserror::Error)]
@@ -53,6 +53,15 @@ pub enum MetadataError {
     /// Storage type does not match requested type.
     #[error("Storage type error")]
     StorageTypeError,
+    /// Storage key does not belong to the storage entry.
+    #[error("Storage key does not start with the storage prefix")]
+    StoragePrefixMismatch,
+    /// The hasher does not keep the key, so it cannot be decoded.
+    #[error("Cannot decode a storage map key hashed with {0:?}")]
+    HasherNotReversible(StorageHasher),
+    /// Failure to decode a storage map key.
+    #[error("Failed to decode storage map key: {0}")]
+    MapKeyError(CodecError),
     /// Default error.
     #[error("Failed to decode default: {0}")]
     DefaultError(CodecError),
@@ -62,6 +71,12 @@ pub enum MetadataError {
     /// Constant is not in metadata.
     #[error("Constant {0} not found")]
     ConstantNotFound(&'static str),
+    /// Signed extension of the runtime is not known to the client.
+    #[error("Signed extension {0} is not supported")]
+    SignedExtensionNotSupported(String),
+    /// Signed extensions of the runtime differ from the ones the client signs with.
+    #[error("Runtime signed extensions {0:?} do not match the client signed extensions {1:?}")]
+    SignedExtensionsMismatch(Vec<String>, Vec<String>),
 }
 
 /// Runtime metadata.
@@ -71,9 +86,37 @@ pub struct Metadata {
     modules_with_calls: HashMap<String, ModuleWithCalls>,
     modules_with_events: HashMap<String, ModuleWithEvents>,
     modules_with_errors: HashMap<String, ModuleWithErrors>,
+    signed_extensions: Vec<String>,
 }
 
 impl Metadata {
+    /// Decodes metadata from its SCALE encoding, as returned by `state_getMetadata`.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        let meta = RuntimeMetadataPrefixed::decode(&mut &bytes[..])?;
+        Ok(Self::try_from(meta)?)
+    }
+
+    /// Returns the identifiers of the signed extensions of the runtime, in encoding order.
+    pub fn signed_extensions(&self) -> &[String] {
+        &self.signed_extensions
+    }
+
+    /// Checks that `identifiers` are the signed extensions of the runtime, in order.
+    pub fn check_signed_extensions(&self, identifiers: &[&str]) -> Result<(), MetadataError> {
+        if self
+            .signed_extensions
+            .iter()
+            .map(String::as_str)
+            .ne(identifiers.iter().copied())
+        {
+            return Err(MetadataError::SignedExtensionsMismatch(
+                self.signed_extensions.clone(),
+                identifiers.iter().map(|id| id.to_string()).collect(),
+            ));
+        }
+        Ok(())
+    }
+
     /// Returns `ModuleMetadata`.
     pub fn module<S>(&self, name: S) -> Result<&ModuleMetadata, MetadataError>
     where
@@ -281,6 +324,71 @@ impl StorageMetadata {
         Self::hash(hasher, &key.encode())
     }
 
+    /// Decodes a key hashed with `hasher` from the start of `input`, advancing it past the key.
+    ///
+    /// Only hashers which append the key to the hash (`Blake2_128Concat`, `Twox64Concat` and
+    /// `Identity`) can be decoded.
+    pub fn unhash_key<K: Decode>(
+        hasher: &StorageHasher,
+        input: &mut &[u8],
+    ) -> Result<K, MetadataError> {
+        let hash_len = match hasher {
+            StorageHasher::Blake2_128Concat => 16,
+            StorageHasher::Twox64Concat => 8,
+            StorageHasher::Identity => 0,
+            _ => return Err(MetadataError::HasherNotReversible(hasher.clone())),
+        };
+        if input.len() < hash_len {
+            return Err(MetadataError::MapKeyError("Storage key too short".into()));
+        }
+        *input = &input[hash_len..];
+        K::decode(input).map_err(MetadataError::MapKeyError)
+    }
+
+    /// Returns the part of `key` following the storage prefix.
+    fn strip_prefix<'a>(&self, key: &'a StorageKey) -> Result<&'a [u8], MetadataError> {
+        let prefix = self.prefix();
+        if key.0.starts_with(&prefix.0) {
+            Ok(&key.0[prefix.0.len()..])
+        } else {
+            Err(MetadataError::StoragePrefixMismatch)
+        }
+    }
+
+    /// Decodes the map key of a storage map entry from its `StorageKey`.
+    pub fn decode_map_key<K: Decode>(&self, key: &StorageKey) -> Result<K, MetadataError> {
+        match &self.ty {
+            StorageEntryType::Map { hasher, .. } => {
+                let mut input = self.strip_prefix(key)?;
+                let key = Self::unhash_key(hasher, &mut input)?;
+                ensure_consumed(input)?;
+                Ok(key)
+            }
+            _ => Err(MetadataError::StorageTypeError),
+        }
+    }
+
+    /// Decodes both map keys of a storage double map entry from its `StorageKey`.
+    pub fn decode_double_map_key<K1: Decode, K2: Decode>(
+        &self,
+        key: &StorageKey,
+    ) -> Result<(K1, K2), MetadataError> {
+        match &self.ty {
+            StorageEntryType::DoubleMap {
+                hasher,
+                key2_hasher,
+                ..
+            } => {
+                let mut input = self.strip_prefix(key)?;
+                let key1 = Self::unhash_key(hasher, &mut input)?;
+                let key2 = Self::unhash_key(key2_hasher, &mut input)?;
+                ensure_consumed(input)?;
+                Ok((key1, key2))
+            }
+            _ => Err(MetadataError::StorageTypeError),
+        }
+    }
+
     pub fn plain(&self) -> Result<StoragePlain, MetadataError> {
         match &self.ty {
             StorageEntryType::Plain(_) => Ok(StoragePlain {
@@ -320,6 +428,16 @@ impl StorageMetadata {
     }
 }
 
+fn ensure_consumed(input: &[u8]) -> Result<(), MetadataError> {
+    if input.is_empty() {
+        Ok(())
+    } else {
+        Err(MetadataError::MapKeyError(
+            "Storage key has trailing bytes".into(),
+        ))
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct StoragePlain {
     prefix: Vec<u8>,
@@ -576,11 +694,18 @@ impl TryFrom<RuntimeMetadataPrefixed> for Metadata {
                 },
             );
         }
+        let signed_extensions = meta
+            .extrinsic
+            .signed_extensions
+            .into_iter()
+            .map(convert)
+            .collect::<Result<_, _>>()?;
         Ok(Metadata {
             modules,
             modules_with_calls,
             modules_with_events,
             modules_with_errors,
+            signed_extensions,
         })
     }
 }
@@ -637,3 +762,46 @@ fn convert_constant(
         documentation,
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn metadata(signed_extensions: &[&str]) -> Metadata {
+        Metadata {
+            modules: HashMap::new(),
+            modules_with_calls: HashMap::new(),
+            modules_with_events: HashMap::new(),
+            modules_with_errors: HashMap::new(),
+            signed_extensions: signed_extensions.iter().map(|id| id.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn test_check_signed_extensions() {
+        let metadata = metadata(&["CheckSpecVersion", "CheckNonce", "CheckWeight"]);
+        metadata
+            .check_signed_extensions(&["CheckSpecVersion", "CheckNonce", "CheckWeight"])
+            .unwrap();
+
+        let missing = metadata.check_signed_extensions(&["CheckSpecVersion", "CheckNonce"]);
+        assert!(matches!(
+            missing,
+            Err(MetadataError::SignedExtensionsMismatch(runtime, client))
+                if runtime.len() == 3 && client.len() == 2
+        ));
+        let reordered =
+            metadata.check_signed_extensions(&["CheckNonce", "CheckSpecVersion", "CheckWeight"]);
+        assert!(matches!(
+            reordered,
+            Err(MetadataError::SignedExtensionsMismatch(..))
+        ));
+        let extra = metadata.check_signed_extensions(&[
+            "CheckSpecVersion",
+            "CheckNonce",
+            "CheckWeight",
+            "CheckFoo",
+        ]);
+        assert!(extra.is_err());
+    }
+}
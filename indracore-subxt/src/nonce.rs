@@ -0,0 +1,360 @@
+// Copyright 2019-2020 Parity Technologies (UK) Ltd.
+// This file is part of substrate-subxt.
+//
+// subxt is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// subxt is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with substrate-subxt.  If not, see <http://www.gnu.org/licenses/>.
+
+use sp_runtime::traits::One;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+};
+
+use crate::{
+    error::Error,
+    frame::system::System,
+    rpc::{Rpc, TransactionStatus},
+    runtimes::Runtime,
+};
+
+struct AccountNonces<T: System> {
+    next: T::Index,
+    in_flight: BTreeSet<T::Index>,
+    /// Nonces released below `next`, handed out again first so they leave no gap.
+    released: BTreeSet<T::Index>,
+    stale: bool,
+    resyncs: u64,
+}
+
+impl<T: System> Default for AccountNonces<T> {
+    fn default() -> Self {
+        Self {
+            next: Default::default(),
+            in_flight: BTreeSet::new(),
+            released: BTreeSet::new(),
+            stale: true,
+            resyncs: 0,
+        }
+    }
+}
+
+impl<T: System> AccountNonces<T> {
+    /// Continues from the node's next index, but above every nonce still in flight.
+    fn sync(&mut self, next: T::Index) {
+        // the node counts the transactions below its next index
+        self.in_flight.retain(|nonce| *nonce >= next);
+        self.next = match self.in_flight.iter().next_back() {
+            Some(highest) => next.max(*highest + One::one()),
+            None => next,
+        };
+        let end = self.next;
+        self.released.retain(|nonce| *nonce >= next && *nonce < end);
+        self.stale = false;
+    }
+
+    fn take(&mut self) -> T::Index {
+        let nonce = match self.released.iter().next().copied() {
+            Some(nonce) => {
+                self.released.remove(&nonce);
+                nonce
+            }
+            None => {
+                let nonce = self.next;
+                self.next += One::one();
+                nonce
+            }
+        };
+        self.in_flight.insert(nonce);
+        nonce
+    }
+
+    fn release(&mut self, nonce: T::Index) {
+        if !self.in_flight.remove(&nonce) {
+            return;
+        }
+        if nonce + One::one() != self.next {
+            self.released.insert(nonce);
+            return;
+        }
+        self.next = nonce;
+        while let Some(last) = self.released.iter().next_back().copied() {
+            if last + One::one() != self.next {
+                break;
+            }
+            self.released.remove(&last);
+            self.next = last;
+        }
+    }
+}
+
+/// Hands out nonces to concurrent tasks submitting from the same accounts.
+///
+/// The first nonce of an account is fetched with `system_accountNextIndex`, which counts the
+/// transactions of the account in the pool, later ones are counted locally. A nonce is in
+/// flight from its reservation until the pool accepts its transaction, or until the
+/// transaction is in a block when its progress is followed. A reservation dropped before its
+/// transaction is submitted releases the nonce, which is handed out again so no gap is left.
+///
+/// A rejected, invalid or dropped transaction makes the account resync with the node on its
+/// next nonce, never going below a nonce still in flight. The node is not queried while the
+/// state is locked. Clones share their state.
+pub struct NonceManager<T: Runtime> {
+    rpc: Rpc<T>,
+    accounts: Arc<Mutex<BTreeMap<T::AccountId, AccountNonces<T>>>>,
+}
+
+impl<T: Runtime> Clone for NonceManager<T> {
+    fn clone(&self) -> Self {
+        Self {
+            rpc: self.rpc.clone(),
+            accounts: self.accounts.clone(),
+        }
+    }
+}
+
+impl<T: Runtime> NonceManager<T> {
+    pub(crate) fn new(rpc: Rpc<T>) -> Self {
+        Self {
+            rpc,
+            accounts: Default::default(),
+        }
+    }
+
+    fn accounts(&self) -> MutexGuard<BTreeMap<T::AccountId, AccountNonces<T>>> {
+        self.accounts.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Reserves the next nonce of `account`, see [`NonceReservation`].
+    pub async fn reserve(&self, account: &T::AccountId) -> Result<NonceReservation<T>, Error> {
+        loop {
+            let resyncs = {
+                let mut accounts = self.accounts();
+                let nonces = accounts.entry(account.clone()).or_default();
+                if !nonces.stale {
+                    let nonce = nonces.take();
+                    return Ok(NonceReservation {
+                        manager: self.clone(),
+                        account: account.clone(),
+                        nonce,
+                        watched: false,
+                        settled: false,
+                    });
+                }
+                nonces.resyncs
+            };
+            let next = self.rpc.account_next_index(account).await?;
+            let mut accounts = self.accounts();
+            let nonces = accounts.entry(account.clone()).or_default();
+            // a resync requested while fetching needs a fresh index
+            if nonces.stale && nonces.resyncs == resyncs {
+                nonces.sync(next);
+            }
+        }
+    }
+
+    /// Marks the transaction of `account` with `nonce` as accepted by the pool or included in a
+    /// block.
+    pub fn complete(&self, account: &T::AccountId, nonce: T::Index) {
+        if let Some(nonces) = self.accounts().get_mut(account) {
+            nonces.in_flight.remove(&nonce);
+        }
+    }
+
+    /// Hands `nonce` of `account` out again, its transaction was not accepted.
+    pub fn release(&self, account: &T::AccountId, nonce: T::Index) {
+        if let Some(nonces) = self.accounts().get_mut(account) {
+            nonces.release(nonce);
+        }
+    }
+
+    /// Fetches the next nonce of `account` from the node again on its next reservation.
+    pub fn resync(&self, account: &T::AccountId) {
+        if let Some(nonces) = self.accounts().get_mut(account) {
+            nonces.stale = true;
+            nonces.resyncs += 1;
+        }
+    }
+
+    /// Updates the nonces of `account` from the pool status of its transaction with `nonce`.
+    ///
+    /// Returns whether the status is final for the nonce.
+    pub fn observe(
+        &self,
+        account: &T::AccountId,
+        nonce: T::Index,
+        status: &TransactionStatus<T::Hash, T::Hash>,
+    ) -> bool {
+        match status {
+            TransactionStatus::InBlock(_)
+            | TransactionStatus::Finalized(_)
+            | TransactionStatus::FinalityTimeout(_) => self.complete(account, nonce),
+            TransactionStatus::Usurped(_) => {
+                self.complete(account, nonce);
+                self.resync(account);
+            }
+            TransactionStatus::Invalid | TransactionStatus::Dropped => {
+                self.release(account, nonce);
+                self.resync(account);
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    /// Returns the nonces of `account` that are in flight.
+    pub fn in_flight(&self, account: &T::AccountId) -> Vec<T::Index> {
+        self.accounts()
+            .get(account)
+            .map(|nonces| nonces.in_flight.iter().copied().collect())
+            .unwrap_or_default()
+    }
+}
+
+/// A nonce reserved by a [`NonceManager`] for a transaction of an account.
+///
+/// Settle the reservation with the outcome of submitting its transaction, or hand it to
+/// `TransactionProgress::track_nonce` to follow the transaction. Dropping an unsubmitted
+/// reservation releases the nonce, dropping a followed one keeps it used.
+pub struct NonceReservation<T: Runtime> {
+    manager: NonceManager<T>,
+    account: T::AccountId,
+    nonce: T::Index,
+    watched: bool,
+    settled: bool,
+}
+
+impl<T: Runtime> NonceReservation<T> {
+    /// Returns the account the nonce is reserved for.
+    pub fn account(&self) -> &T::AccountId {
+        &self.account
+    }
+
+    /// Returns the reserved nonce.
+    pub fn nonce(&self) -> T::Index {
+        self.nonce
+    }
+
+    /// Settles the reservation with the outcome of submitting its transaction, see
+    /// [`NonceReservation::accept`] and [`NonceReservation::reject`].
+    pub fn settle<R>(self, result: &Result<R, Error>) {
+        match result {
+            Ok(_) => self.accept(),
+            Err(err) => self.reject(err),
+        }
+    }
+
+    /// Completes the nonce, its transaction was accepted by the pool.
+    pub fn accept(mut self) {
+        self.manager.complete(&self.account, self.nonce);
+        self.settled = true;
+    }
+
+    /// Releases the nonce, its transaction was not accepted. An invalid transaction also
+    /// resyncs the account with the node.
+    pub fn reject(mut self, error: &Error) {
+        self.manager.release(&self.account, self.nonce);
+        if let Error::Invalid(_) = error {
+            self.manager.resync(&self.account);
+        }
+        self.settled = true;
+    }
+
+    /// Updates the nonce from a pool status of its submitted transaction.
+    pub(crate) fn observe(&mut self, status: &TransactionStatus<T::Hash, T::Hash>) {
+        self.watched = true;
+        if !self.settled {
+            self.settled = self.manager.observe(&self.account, self.nonce, status);
+        }
+    }
+
+    pub(crate) fn watch(&mut self) {
+        self.watched = true;
+    }
+}
+
+impl<T: Runtime> Drop for NonceReservation<T> {
+    fn drop(&mut self) {
+        if self.settled {
+            return;
+        }
+        if self.watched {
+            self.manager.complete(&self.account, self.nonce)
+        } else {
+            self.manager.release(&self.account, self.nonce)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtimes::IndracoreRuntime;
+
+    fn synced(next: u32) -> AccountNonces<IndracoreRuntime> {
+        let mut nonces = AccountNonces::default();
+        nonces.sync(next);
+        nonces
+    }
+
+    #[test]
+    fn test_release_leaves_no_gap() {
+        let mut nonces = synced(5);
+        assert_eq!((nonces.take(), nonces.take(), nonces.take()), (5, 6, 7));
+
+        // a nonce below others in flight is handed out again first
+        nonces.release(6);
+        assert_eq!(nonces.take(), 6);
+        assert_eq!(nonces.take(), 8);
+
+        // releasing the highest nonces rolls the next nonce back
+        nonces.release(6);
+        nonces.release(8);
+        nonces.release(7);
+        assert_eq!(nonces.next, 6);
+        assert!(nonces.released.is_empty());
+        assert_eq!(nonces.take(), 6);
+    }
+
+    #[test]
+    fn test_completed_nonce_is_not_released() {
+        let mut nonces = synced(0);
+        let nonce = nonces.take();
+        nonces.in_flight.remove(&nonce);
+        nonces.release(nonce);
+        assert_eq!(nonces.take(), 1);
+    }
+
+    #[test]
+    fn test_sync_stays_above_in_flight() {
+        let mut nonces = synced(3);
+        assert_eq!((nonces.take(), nonces.take()), (3, 4));
+
+        // the node only counts the submitted nonce 3
+        nonces.stale = true;
+        nonces.sync(4);
+        assert_eq!(
+            nonces.in_flight.iter().copied().collect::<Vec<_>>(),
+            vec![4]
+        );
+        assert_eq!(nonces.take(), 5);
+
+        // a lower index from the node does not hand out 4 or 5 twice
+        nonces.sync(2);
+        assert_eq!(nonces.take(), 6);
+
+        // released nonces the node already counts are dropped
+        nonces.release(5);
+        nonces.sync(6);
+        assert_eq!(nonces.take(), 7);
+    }
+}
@@ -14,14 +14,15 @@
 // You should have received a copy of the GNU General Public License
 // along with substrate-subxt.  If not, see <http://www.gnu.org/licenses/>.
 
-// jsonrpsee subscriptions are interminable.
-// Allows `while let status = subscription.next().await {}`
-// Related: https://github.com/paritytech/substrate-subxt/issues/66
-#![allow(irrefutable_let_patterns)]
-
 use codec::{Decode, Encode, Error as CodecError};
-use core::{convert::TryInto, marker::PhantomData};
-use frame_metadata::RuntimeMetadataPrefixed;
+use core::marker::PhantomData;
+use frame_support::weights::{DispatchClass, Weight};
+use futures::{
+    future::{self, Either},
+    stream::{self, BoxStream, Stream, StreamExt},
+    task::{Context, Poll},
+};
+use futures_timer::Delay;
 use jsonrpsee::{
     client::Subscription,
     common::{to_value as to_json_value, Params},
@@ -35,19 +36,28 @@ use sp_core::{
 use sp_rpc::{list::ListOrValue, number::NumberOrHex};
 use sp_runtime::{
     generic::{Block, SignedBlock},
-    traits::Hash,
+    traits::{Hash, UniqueSaturatedInto},
+    ApplyExtrinsicResult,
 };
 use sp_version::RuntimeVersion;
+use std::{pin::Pin, time::Duration};
 
 use crate::{
-    error::Error,
-    events::{EventsDecoder, RawEvent},
-    frame::{system::System, Event},
+    error::{Error, TransactionError},
+    events::{BlockEvents, EventsDecoder, Raw, RawEvent},
+    frame::{
+        balances::Balances,
+        system::{Phase, System},
+        Event,
+    },
     metadata::Metadata,
+    nonce::NonceReservation,
     runtimes::Runtime,
-    subscription::EventSubscription,
 };
 
+/// Time to wait for the events of an extrinsic once it is in a block.
+pub const DEFAULT_EVENTS_TIMEOUT: Duration = Duration::from_secs(30);
+
 pub type ChainBlock<T> = SignedBlock<Block<<T as System>::Header, <T as System>::Extrinsic>>;
 
 /// Wrapper for NumberOrHex to allow custom From impls
@@ -126,9 +136,42 @@ pub struct ReadProof<Hash> {
     pub proof: Vec<Bytes>,
 }
 
+/// Information related to a dispatchable's class, weight, and fee that can be queried from
+/// the runtime.
+///
+/// # Note
+///
+/// This is copied from `pallet-transaction-payment-rpc-runtime-api` to avoid a dependency on
+/// that crate. Therefore it must be kept compatible with that type from the target substrate
+/// version.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[serde(bound(deserialize = "Balance: std::str::FromStr"))]
+pub struct RuntimeDispatchInfo<Balance> {
+    /// Weight of this dispatch.
+    pub weight: Weight,
+    /// Class of this dispatch.
+    pub class: DispatchClass,
+    /// The partial inclusion fee of this dispatch. This does not include tip or anything
+    /// else that depends on the signature (i.e. depends on a `SignedExtension`).
+    #[serde(deserialize_with = "deserialize_from_string")]
+    pub partial_fee: Balance,
+}
+
+fn deserialize_from_string<'de, D, B>(deserializer: D) -> Result<B, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    B: std::str::FromStr,
+{
+    let s = String::deserialize(deserializer)?;
+    s.parse::<B>()
+        .map_err(|_| serde::de::Error::custom("Parse from string failed"))
+}
+
 /// Client for substrate rpc interfaces
 pub struct Rpc<T: Runtime> {
     client: Client,
+    events_timeout: Duration,
     marker: PhantomData<T>,
 }
 
@@ -136,6 +179,7 @@ impl<T: Runtime> Clone for Rpc<T> {
     fn clone(&self) -> Self {
         Self {
             client: self.client.clone(),
+            events_timeout: self.events_timeout,
             marker: PhantomData,
         }
     }
@@ -145,10 +189,16 @@ impl<T: Runtime> Rpc<T> {
     pub fn new(client: Client) -> Self {
         Self {
             client,
+            events_timeout: DEFAULT_EVENTS_TIMEOUT,
             marker: PhantomData,
         }
     }
 
+    /// Set how long to wait for the events of an extrinsic once it is in a block
+    pub fn set_events_timeout(&mut self, timeout: Duration) {
+        self.events_timeout = timeout;
+    }
+
     /// Fetch a storage key
     pub async fn storage(
         &self,
@@ -229,13 +279,23 @@ impl<T: Runtime> Rpc<T> {
 
     /// Fetch the metadata
     pub async fn metadata(&self) -> Result<Metadata, Error> {
-        let bytes: Bytes = self
+        let bytes = self.metadata_bytes().await?;
+        Metadata::from_bytes(&bytes)
+    }
+
+    /// Fetch the metadata of the runtime at a block
+    pub async fn metadata_at(&self, at: Option<T::Hash>) -> Result<Metadata, Error> {
+        let params = Params::Array(vec![to_json_value(at)?]);
+        let bytes: Bytes = self.client.request("state_getMetadata", params).await?;
+        Metadata::from_bytes(&bytes)
+    }
+
+    /// Fetch the SCALE encoded metadata
+    pub async fn metadata_bytes(&self) -> Result<Bytes, Error> {
+        Ok(self
             .client
             .request("state_getMetadata", Params::None)
-            .await?;
-        let meta: RuntimeMetadataPrefixed = Decode::decode(&mut &bytes[..])?;
-        let metadata: Metadata = meta.try_into()?;
-        Ok(metadata)
+            .await?)
     }
 
     /// Fetch system properties
@@ -246,6 +306,16 @@ impl<T: Runtime> Rpc<T> {
             .await?)
     }
 
+    /// Fetch the next nonce of an account, counting its transactions in the pool
+    pub async fn account_next_index(&self, account: &T::AccountId) -> Result<T::Index, Error> {
+        let params = Params::Array(vec![to_json_value(account)?]);
+        let index: u64 = self
+            .client
+            .request("system_accountNextIndex", params)
+            .await?;
+        Ok(index.unique_saturated_into())
+    }
+
     /// Get a header
     pub async fn header(&self, hash: Option<T::Hash>) -> Result<Option<T::Header>, Error> {
         let params = Params::Array(vec![to_json_value(hash)?]);
@@ -306,11 +376,10 @@ impl<T: Runtime> Rpc<T> {
 
     /// Subscribe to substrate System Events
     pub async fn subscribe_events(&self) -> Result<Subscription<StorageChangeSet<T::Hash>>, Error> {
-        let mut storage_key = twox_128(b"System").to_vec();
-        storage_key.extend(twox_128(b"Events").to_vec());
-        log::debug!("Events storage key {:?}", hex::encode(&storage_key));
+        let storage_key = events_key();
+        log::debug!("Events storage key {:?}", hex::encode(&storage_key.0));
 
-        let keys = Some(vec![StorageKey(storage_key)]);
+        let keys = Some(vec![storage_key]);
         let params = Params::Array(vec![to_json_value(keys)?]);
 
         let subscription = self
@@ -354,7 +423,8 @@ impl<T: Runtime> Rpc<T> {
         let xt_hash = self
             .client
             .request("author_submitExtrinsic", params)
-            .await?;
+            .await
+            .map_err(Error::from_submission)?;
         Ok(xt_hash)
     }
 
@@ -371,7 +441,8 @@ impl<T: Runtime> Rpc<T> {
                 params,
                 "author_unwatchExtrinsic",
             )
-            .await?;
+            .await
+            .map_err(Error::from_submission)?;
         Ok(subscription)
     }
 
@@ -381,70 +452,122 @@ impl<T: Runtime> Rpc<T> {
         extrinsic: E,
         decoder: EventsDecoder<T>,
     ) -> Result<ExtrinsicSuccess<T>, Error> {
+        self.watch_progress_with_events(extrinsic, decoder)
+            .await?
+            .wait_for_in_block()
+            .await
+    }
+
+    /// Submit an extrinsic and follow its progress through the transaction pool
+    ///
+    /// Events are read from the storage of the block once waited for, see
+    /// [`Rpc::watch_progress_with_events`].
+    pub async fn watch_progress<E: Encode>(
+        &self,
+        extrinsic: E,
+        decoder: EventsDecoder<T>,
+    ) -> Result<TransactionProgress<T>, Error> {
+        self.progress(extrinsic, decoder, None).await
+    }
+
+    /// Submit an extrinsic and follow its progress through the transaction pool, subscribing
+    /// to the events of every block so those of its block arrive without another request
+    pub async fn watch_progress_with_events<E: Encode>(
+        &self,
+        extrinsic: E,
+        decoder: EventsDecoder<T>,
+    ) -> Result<TransactionProgress<T>, Error> {
+        // subscribed before submitting so the events of the block are not missed
+        let events = self.subscribe_events().await.ok();
+        self.progress(extrinsic, decoder, events).await
+    }
+
+    async fn progress<E: Encode>(
+        &self,
+        extrinsic: E,
+        decoder: EventsDecoder<T>,
+        events: Option<Subscription<StorageChangeSet<T::Hash>>>,
+    ) -> Result<TransactionProgress<T>, Error> {
         let ext_hash = T::Hashing::hash_of(&extrinsic);
         log::info!("Submitting Extrinsic `{:?}`", ext_hash);
+        let subscription = self.watch_extrinsic(extrinsic).await?;
+        let statuses = stream::unfold(subscription, |mut subscription| async move {
+            let status = subscription.next().await;
+            Some((status, subscription))
+        });
+        Ok(TransactionProgress {
+            rpc: self.clone(),
+            statuses: statuses.boxed(),
+            events,
+            extrinsic: ext_hash,
+            decoder,
+            nonce: None,
+            finished: false,
+        })
+    }
 
-        let events_sub = self.subscribe_events().await?;
-        let mut xt_sub = self.watch_extrinsic(extrinsic).await?;
+    /// Fetch and decode every event of a block
+    pub async fn events_at(
+        &self,
+        block_hash: T::Hash,
+        decoder: &EventsDecoder<T>,
+    ) -> Result<BlockEvents<T>, Error> {
+        let events = match self.storage(&events_key(), Some(block_hash)).await? {
+            Some(data) => decoder.decode_events(&mut &data.0[..])?,
+            None => vec![],
+        };
+        Ok(BlockEvents {
+            block: block_hash,
+            events,
+        })
+    }
 
-        while let status = xt_sub.next().await {
-            // log::info!("received status {:?}", status);
-            match status {
-                // ignore in progress extrinsic for now
-                TransactionStatus::Future
-                | TransactionStatus::Ready
-                | TransactionStatus::Broadcast(_) => continue,
-                TransactionStatus::InBlock(block_hash) => {
-                    log::info!("Fetching block {:?}", block_hash);
-                    let block = self.block(Some(block_hash)).await?;
-                    return match block {
-                        Some(signed_block) => {
-                            log::info!(
-                                "Found block {:?}, with {} extrinsics",
-                                block_hash,
-                                signed_block.block.extrinsics.len()
-                            );
-                            let ext_index = signed_block
-                                .block
-                                .extrinsics
-                                .iter()
-                                .position(|ext| {
-                                    let hash = T::Hashing::hash_of(ext);
-                                    hash == ext_hash
-                                })
-                                .ok_or_else(|| {
-                                    Error::Other(format!(
-                                        "Failed to find Extrinsic with hash {:?}",
-                                        ext_hash,
-                                    ))
-                                })?;
-                            let mut sub = EventSubscription::new(events_sub, decoder);
-                            sub.filter_extrinsic(block_hash, ext_index);
-                            let mut events = vec![];
-                            while let Some(event) = sub.next().await {
-                                events.push(event?);
-                            }
-                            Ok(ExtrinsicSuccess {
-                                block: block_hash,
-                                extrinsic: ext_hash,
-                                events,
-                            })
-                        }
-                        None => Err(format!("Failed to find block {:?}", block_hash).into()),
-                    };
-                }
-                TransactionStatus::Invalid => return Err("Extrinsic Invalid".into()),
-                TransactionStatus::Usurped(_) => return Err("Extrinsic Usurped".into()),
-                TransactionStatus::Dropped => return Err("Extrinsic Dropped".into()),
-                TransactionStatus::Retracted(_) => return Err("Extrinsic Retracted".into()),
-                // should have made it `InBlock` before either of these
-                TransactionStatus::Finalized(_) => return Err("Extrinsic Finalized".into()),
-                TransactionStatus::FinalityTimeout(_) => {
-                    return Err("Extrinsic FinalityTimeout".into())
-                }
-            }
-        }
-        unreachable!()
+    /// Find the index of an extrinsic in a block
+    pub async fn extrinsic_index(
+        &self,
+        block_hash: T::Hash,
+        ext_hash: T::Hash,
+    ) -> Result<usize, Error> {
+        log::info!("Fetching block {:?}", block_hash);
+        let block = self
+            .block(Some(block_hash))
+            .await?
+            .ok_or_else(|| Error::Other(format!("Failed to find block {:?}", block_hash)))?;
+        block
+            .block
+            .extrinsics
+            .iter()
+            .position(|ext| T::Hashing::hash_of(ext) == ext_hash)
+            .ok_or_else(|| {
+                Error::Other(format!("Failed to find Extrinsic with hash {:?}", ext_hash))
+            })
+    }
+
+    /// Query the weight, class and partial fee of an extrinsic.
+    pub async fn query_info<E: Encode>(
+        &self,
+        extrinsic: E,
+        at: Option<T::Hash>,
+    ) -> Result<RuntimeDispatchInfo<T::Balance>, Error>
+    where
+        T: Balances,
+    {
+        let bytes: Bytes = extrinsic.encode().into();
+        let params = Params::Array(vec![to_json_value(bytes)?, to_json_value(at)?]);
+        let info = self.client.request("payment_queryInfo", params).await?;
+        Ok(info)
+    }
+
+    /// Dry run an extrinsic at a block, the best block by default.
+    pub async fn dry_run<E: Encode>(
+        &self,
+        extrinsic: E,
+        at: Option<T::Hash>,
+    ) -> Result<ApplyExtrinsicResult, Error> {
+        let bytes: Bytes = extrinsic.encode().into();
+        let params = Params::Array(vec![to_json_value(bytes)?, to_json_value(at)?]);
+        let result: Bytes = self.client.request("system_dryRun", params).await?;
+        Ok(ApplyExtrinsicResult::decode(&mut &result[..])?)
     }
 
     /// Insert a key into the keystore.
@@ -490,6 +613,187 @@ impl<T: Runtime> Rpc<T> {
     }
 }
 
+/// Progress of an extrinsic through the transaction pool.
+///
+/// A [`Stream`] of every status until a final one. A block the extrinsic was included in may
+/// be retracted by a re-org, after which the extrinsic can be included in another block.
+pub struct TransactionProgress<T: Runtime> {
+    rpc: Rpc<T>,
+    statuses: BoxStream<'static, TransactionStatus<T::Hash, T::Hash>>,
+    events: Option<Subscription<StorageChangeSet<T::Hash>>>,
+    extrinsic: T::Hash,
+    decoder: EventsDecoder<T>,
+    nonce: Option<NonceReservation<T>>,
+    finished: bool,
+}
+
+impl<T: Runtime> TransactionProgress<T> {
+    /// Returns the hash of the extrinsic.
+    pub fn extrinsic_hash(&self) -> T::Hash {
+        self.extrinsic
+    }
+
+    /// Keeps the reserved nonce of the extrinsic in flight until a status shows it in a block,
+    /// or releases it if the extrinsic is dropped from the pool.
+    pub fn track_nonce(mut self, mut reservation: NonceReservation<T>) -> Self {
+        reservation.watch();
+        self.nonce = Some(reservation);
+        self
+    }
+
+    /// Gets the next status, `None` after a final status.
+    pub async fn next(&mut self) -> Option<TransactionStatus<T::Hash, T::Hash>> {
+        StreamExt::next(self).await
+    }
+
+    /// Waits until the extrinsic is in a block and returns its events.
+    ///
+    /// Returns the events of the block the extrinsic is in when the status is seen, a later
+    /// re-org is not followed.
+    pub async fn wait_for_in_block(mut self) -> Result<ExtrinsicSuccess<T>, Error> {
+        while let Some(status) = self.next().await {
+            match status {
+                TransactionStatus::InBlock(hash) | TransactionStatus::Finalized(hash) => {
+                    return self.events(hash).await
+                }
+                status => self.check(status)?,
+            }
+        }
+        Err("Transaction progress finished without a block".into())
+    }
+
+    /// Waits until the block of the extrinsic is finalized and returns its events.
+    ///
+    /// Blocks retracted by a re-org are skipped until the extrinsic is in a finalized block.
+    pub async fn wait_for_finalized(mut self) -> Result<ExtrinsicSuccess<T>, Error> {
+        while let Some(status) = self.next().await {
+            match status {
+                TransactionStatus::Finalized(hash) => return self.events(hash).await,
+                status => self.check(status)?,
+            }
+        }
+        Err("Transaction progress finished without a finalized block".into())
+    }
+
+    /// Fetches the events of the extrinsic in a block, failing with `Error::EventsTimeout` if
+    /// they take longer than the timeout of the client.
+    async fn events(&mut self, block_hash: T::Hash) -> Result<ExtrinsicSuccess<T>, Error> {
+        let timeout = self.rpc.events_timeout;
+        let events = Box::pin(self.fetch_events(block_hash));
+        match future::select(events, Delay::new(timeout)).await {
+            Either::Left((events, _)) => events,
+            Either::Right(_) => Err(Error::EventsTimeout(timeout)),
+        }
+    }
+
+    /// Reads the events from the storage of the block, taking them from the events
+    /// subscription instead if it notifies the block first.
+    async fn fetch_events(&mut self, block_hash: T::Hash) -> Result<ExtrinsicSuccess<T>, Error> {
+        let ext_index = self.rpc.extrinsic_index(block_hash, self.extrinsic).await?;
+        let key = events_key();
+        let fetched = Box::pin(self.rpc.storage(&key, Some(block_hash)));
+        let data = match self.events.as_mut() {
+            Some(events) => {
+                let notified = Box::pin(find_change(events, block_hash, &key));
+                match future::select(notified, fetched).await {
+                    Either::Left((data, _)) => data,
+                    Either::Right((data, _)) => data?,
+                }
+            }
+            None => fetched.await?,
+        };
+        let raw_events = match data {
+            Some(data) => self.decoder.decode_events(&mut &data.0[..])?,
+            None => vec![],
+        };
+        let mut events = vec![];
+        for (phase, raw) in raw_events {
+            if let Phase::ApplyExtrinsic(index) = phase {
+                if index as usize != ext_index {
+                    continue;
+                }
+                match raw {
+                    Raw::Event(event) => events.push(event),
+                    Raw::Error(err) => return Err(err.into()),
+                }
+            }
+        }
+        Ok(ExtrinsicSuccess {
+            block: block_hash,
+            extrinsic: self.extrinsic,
+            events,
+        })
+    }
+
+    /// Fails on a status the extrinsic will not be included after.
+    fn check(&self, status: TransactionStatus<T::Hash, T::Hash>) -> Result<(), Error> {
+        match TransactionError::from_status(self.extrinsic, &status) {
+            Some(error) => Err(error.into()),
+            None => Ok(()),
+        }
+    }
+}
+
+// no field is structurally pinned
+impl<T: Runtime> Unpin for TransactionProgress<T> {}
+
+impl<T: Runtime> Stream for TransactionProgress<T> {
+    type Item = TransactionStatus<T::Hash, T::Hash>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        if this.finished {
+            return Poll::Ready(None);
+        }
+        let status = match this.statuses.poll_next_unpin(cx) {
+            Poll::Ready(Some(status)) => status,
+            Poll::Ready(None) => {
+                this.finished = true;
+                return Poll::Ready(None);
+            }
+            Poll::Pending => return Poll::Pending,
+        };
+        if let Some(reservation) = this.nonce.as_mut() {
+            reservation.observe(&status);
+        }
+        this.finished = matches!(
+            status,
+            TransactionStatus::Finalized(_)
+                | TransactionStatus::FinalityTimeout(_)
+                | TransactionStatus::Usurped(_)
+                | TransactionStatus::Dropped
+                | TransactionStatus::Invalid
+        );
+        Poll::Ready(Some(status))
+    }
+}
+
+/// Waits for the change of `key` in `block_hash`, skipping the change sets of other blocks.
+async fn find_change<Hash: PartialEq>(
+    subscription: &mut Subscription<StorageChangeSet<Hash>>,
+    block_hash: Hash,
+    key: &StorageKey,
+) -> Option<StorageData> {
+    loop {
+        let change_set = subscription.next().await;
+        if change_set.block != block_hash {
+            continue;
+        }
+        return change_set
+            .changes
+            .into_iter()
+            .find(|(k, _)| k == key)
+            .and_then(|(_, data)| data);
+    }
+}
+
+/// Returns the storage key of the events of the current block, `System.Events`.
+pub fn events_key() -> StorageKey {
+    let mut storage_key = twox_128(b"System").to_vec();
+    storage_key.extend(twox_128(b"Events").to_vec());
+    StorageKey(storage_key)
+}
+
 /// Captures data for when an extrinsic is successfully included in a block
 #[derive(Debug)]
 pub struct ExtrinsicSuccess<T: System> {
@@ -125,14 +125,16 @@ impl_opaque_keys! {
 }
 
 use crate::{
+    events::EventsDecoder,
     extrinsic::{DefaultExtra, SignedExtra},
     frame::{
-        balances::{AccountData, Balances},
-        contracts::Contracts,
-        session::Session,
-        staking::Staking,
-        sudo::Sudo,
-        system::System,
+        balances::{AccountData, Balances, BalancesEventsDecoder},
+        contracts::{Contracts, ContractsEventsDecoder},
+        session::{Session, SessionEventsDecoder},
+        staking::{Staking, StakingEventsDecoder},
+        sudo::{Sudo, SudoEventsDecoder},
+        system::{System, SystemEventsDecoder},
+        utility::{Utility, UtilityEventsDecoder},
     },
 };
 
@@ -142,6 +144,14 @@ pub trait Runtime: System + Sized + Send + Sync + 'static {
     type Signature: Verify + Encode + Send + Sync + 'static;
     /// Transaction extras.
     type Extra: SignedExtra<Self> + Send + Sync + 'static;
+
+    /// Registers the type sizes of the events of every module of the runtime.
+    ///
+    /// Registers the types of the `System` module by default, runtimes with other modules
+    /// override it to register theirs.
+    fn register_type_sizes(decoder: &mut EventsDecoder<Self>) {
+        decoder.with_system();
+    }
 }
 
 /// Concrete type definitions compatible with those for kusama, v0.7
@@ -156,6 +166,25 @@ pub struct IndracoreRuntime;
 impl Runtime for IndracoreRuntime {
     type Signature = MultiSignature;
     type Extra = DefaultExtra<Self>;
+
+    fn register_type_sizes(decoder: &mut EventsDecoder<Self>) {
+        decoder.with_balances();
+        decoder.with_contracts();
+        decoder.with_session();
+        decoder.with_staking();
+        decoder.with_sudo();
+        decoder.with_utility();
+        // event arguments of runtime modules without a client side trait
+        decoder.register_type_size::<u64>("Moment");
+        decoder.register_type_size::<u64>("Weight");
+        decoder.register_type_size::<[u8; 32]>("CallHash");
+        decoder
+            .register_type_size::<(<Self as System>::BlockNumber, u32)>("Timepoint<BlockNumber>");
+        decoder.register_type_size::<u32>("RegistrarIndex");
+        decoder.register_type_size::<u32>("BountyIndex");
+        decoder.register_type_size::<u32>("ParaId");
+        decoder.register_type_size::<[u8; 20]>("EthereumAddress");
+    }
 }
 
 impl System for IndracoreRuntime {
@@ -184,3 +213,5 @@ impl Balances for IndracoreRuntime {
 impl Sudo for IndracoreRuntime {}
 
 impl Contracts for IndracoreRuntime {}
+
+impl Utility for IndracoreRuntime {}
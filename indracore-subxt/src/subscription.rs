@@ -94,23 +94,21 @@ impl<T: Runtime> EventSubscription<T> {
                         Err(error) => return Some(Err(error)),
                     };
                     for (phase, raw) in raw_events {
-                        if let Phase::ApplyExtrinsic(i) = phase {
-                            if let Some(ext_index) = self.extrinsic {
-                                if i as usize != ext_index {
-                                    continue;
-                                }
+                        if let Some(ext_index) = self.extrinsic {
+                            if phase != Phase::ApplyExtrinsic(ext_index as u32) {
+                                continue;
                             }
-                            let event = match raw {
-                                Raw::Event(event) => event,
-                                Raw::Error(err) => return Some(Err(err.into())),
-                            };
-                            if let Some((module, variant)) = self.event {
-                                if event.module != module || event.variant != variant {
-                                    continue;
-                                }
+                        }
+                        let event = match raw {
+                            Raw::Event(event) => event,
+                            Raw::Error(err) => return Some(Err(err.into())),
+                        };
+                        if let Some((module, variant)) = self.event {
+                            if event.module != module || event.variant != variant {
+                                continue;
                             }
-                            self.events.push_back(event);
                         }
+                        self.events.push_back(event);
                     }
                 }
             }
@@ -1,12 +1,29 @@
-use crate::utils::keyring::IndraKeyring;
-use indracore_subxt::{system::AccountStoreExt, ClientBuilder, Error, IndracoreRuntime};
+use crate::{
+    indra::Indra,
+    utils::{keyring::IntoAccountId, token::TokenAmount},
+};
+use codec::Decode;
+use futures::{
+    stream::{self, Stream, StreamExt, TryStreamExt},
+    task::{Context, Poll},
+};
+use indracore_subxt::{
+    balances::{AccountData, LocksStoreExt, Reasons},
+    sp_core::{crypto::AccountId32, storage::StorageKey, H256},
+    staking::{BondedStoreExt, LedgerStoreExt, NominatorsStoreExt},
+    system::{AccountStore, AccountStoreExt},
+    Error, IndracoreRuntime, Store,
+};
+use std::pin::Pin;
+
+type SystemAccountInfo = indracore_subxt::system::AccountInfo<IndracoreRuntime>;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct BalanceInfo {
-    pub free: u128,
-    pub reserved: u128,
-    pub misc_frozen: u128,
-    pub fee_frozen: u128,
+    pub free: TokenAmount,
+    pub reserved: TokenAmount,
+    pub misc_frozen: TokenAmount,
+    pub fee_frozen: TokenAmount,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -16,95 +33,392 @@ pub struct AccountInfo {
     pub data: BalanceInfo,
 }
 
+/// A lock on part of an account's free balance.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LockInfo {
+    /// The lock identifier, e.g. `staking` or `democrac`.
+    pub id: String,
+    pub amount: TokenAmount,
+    pub reasons: Reasons,
+}
+
+/// Staking status of a stash account.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StakingInfo {
+    pub stash: AccountId32,
+    pub controller: AccountId32,
+    /// Total amount bonded, including funds being unbonded.
+    pub total: TokenAmount,
+    /// Amount bonded and at stake in the current era.
+    pub active: TokenAmount,
+    /// Amount scheduled to be unbonded.
+    pub unlocking: TokenAmount,
+    /// Validators nominated by the stash, empty if it isn't nominating.
+    pub nominations: Vec<AccountId32>,
+}
+
+/// Everything known about an account, read from the state of a single block.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AccountOverview {
+    /// Block the overview was read at.
+    pub at: H256,
+    pub nonce: u32,
+    pub refcount: u32,
+    pub data: BalanceInfo,
+    pub locks: Vec<LockInfo>,
+    /// Free balance not frozen by any lock, i.e. what the account can transfer or spend on fees.
+    pub transferable: TokenAmount,
+    /// Staking status if the account is a bonded stash or controller.
+    pub staking: Option<StakingInfo>,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct AllAccount {
-    pub accountid: Vec<u8>,
-    pub balance: u128,
+    pub accountid: AccountId32,
+    pub balance: TokenAmount,
+}
+
+/// Options for streaming every account of the chain.
+#[derive(Debug, Clone)]
+pub struct AccountExport {
+    at: Option<H256>,
+    cursor: Option<StorageKey>,
+    page_size: u32,
+    concurrency: usize,
+}
+
+impl Default for AccountExport {
+    fn default() -> Self {
+        Self {
+            at: None,
+            cursor: None,
+            page_size: 256,
+            concurrency: 4,
+        }
+    }
+}
+
+impl AccountExport {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Reads the accounts at this block instead of the current best block.
+    pub fn set_at(mut self, hash: H256) -> Self {
+        self.at = Some(hash);
+        self
+    }
+
+    /// Resumes after the account stored under `key`, the key of the last exported account.
+    pub fn set_cursor(mut self, key: StorageKey) -> Self {
+        self.cursor = Some(key);
+        self
+    }
+
+    /// Sets the number of accounts fetched per request.
+    pub fn set_page_size(mut self, size: u32) -> Self {
+        self.page_size = size;
+        self
+    }
+
+    /// Sets how many pages are fetched in parallel.
+    pub fn set_concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+}
+
+/// Stream of every account at one block, in storage key order.
+///
+/// Each item carries the account's storage key; passing the last key seen to
+/// [`AccountExport::set_cursor`] together with [`AccountStream::at`] resumes the export where
+/// it stopped.
+pub struct AccountStream {
+    at: H256,
+    inner: Pin<Box<dyn Stream<Item = Result<(StorageKey, AllAccount), Error>> + Send>>,
+}
+
+impl AccountStream {
+    /// Returns the block the accounts are read at.
+    pub fn at(&self) -> H256 {
+        self.at
+    }
+}
+
+impl Stream for AccountStream {
+    type Item = Result<(StorageKey, AllAccount), Error>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.inner.as_mut().poll_next(cx)
+    }
 }
 
 pub struct Account {}
 
 impl Account {
-    pub fn fecth_all_account(url: &'static str) -> Result<Vec<AllAccount>, Error> {
-        async_std::task::block_on(async move {
-            let client = match ClientBuilder::<IndracoreRuntime>::new()
-                .set_url(url)
-                .build()
-                .await
-            {
-                Ok(c) => c,
-                Err(e) => return Err(e),
-            };
-            let mut iter = match client.account_iter(None).await {
-                Ok(i) => i,
-                Err(e) => return Err(e),
-            };
-            let mut account_data: Vec<AllAccount> = Vec::new();
-            while let Some((key, account)) = iter.next().await? {
-                let data = AllAccount {
-                    accountid: key.0,
-                    balance: account.data.free,
-                };
-                account_data.push(data);
+    pub async fn fecth_all_account(url: impl AsRef<str>) -> Result<Vec<AllAccount>, Error> {
+        Indra::new(url).await?.fecth_all_account().await
+    }
+
+    pub async fn get_account_info(
+        url: impl AsRef<str>,
+        account_id: impl IntoAccountId,
+    ) -> Result<AccountInfo, Error> {
+        Indra::new(url).await?.get_account_info(account_id).await
+    }
+
+    pub async fn get_account_overview(
+        url: impl AsRef<str>,
+        account_id: impl IntoAccountId,
+    ) -> Result<AccountOverview, Error> {
+        Indra::new(url)
+            .await?
+            .get_account_overview(account_id, None)
+            .await
+    }
+}
+
+impl Indra {
+    pub async fn fecth_all_account(&self) -> Result<Vec<AllAccount>, Error> {
+        self.account_stream(AccountExport::new())
+            .await?
+            .map_ok(|(_, account)| account)
+            .try_collect()
+            .await
+    }
+
+    /// Streams every account on chain without holding them all in memory.
+    ///
+    /// Keys are paged sequentially while the values of up to `concurrency` pages are fetched in
+    /// parallel; accounts are still yielded in storage key order.
+    pub async fn account_stream(&self, export: AccountExport) -> Result<AccountStream, Error> {
+        let at = match export.at {
+            Some(hash) => hash,
+            None => self
+                .client()
+                .block_hash(None)
+                .await?
+                .ok_or_else(|| Error::Other("Best block hash not found".into()))?,
+        };
+        let page_size = export.page_size;
+
+        // the state is the key to continue after, the stream ends with the first empty page
+        let pages = stream::try_unfold(export.cursor, {
+            let indra = self.clone();
+            move |start| {
+                let indra = indra.clone();
+                async move {
+                    let keys = indra
+                        .client()
+                        .fetch_keys::<AccountStore<IndracoreRuntime>>(page_size, start, Some(at))
+                        .await?;
+                    if keys.is_empty() {
+                        return Ok(None);
+                    }
+                    let next = keys.last().cloned();
+                    Ok::<_, Error>(Some((keys, next)))
+                }
             }
-            Ok(account_data)
+        });
+
+        let indra = self.clone();
+        let inner = pages
+            .map_ok(move |keys| {
+                let indra = indra.clone();
+                async move { indra.fetch_account_page(keys, at).await }
+            })
+            .try_buffered(export.concurrency)
+            .map_ok(|page| stream::iter(page.into_iter().map(Ok::<_, Error>)))
+            .try_flatten();
+
+        Ok(AccountStream {
+            at,
+            inner: Box::pin(inner),
         })
     }
 
-    pub fn get_account_info(
-        url: &'static str,
-        account_id: &'static str,
+    async fn fetch_account_page(
+        &self,
+        keys: Vec<StorageKey>,
+        at: H256,
+    ) -> Result<Vec<(StorageKey, AllAccount)>, Error> {
+        let storage = self
+            .client()
+            .metadata()
+            .module(<AccountStore<IndracoreRuntime> as Store<IndracoreRuntime>>::MODULE)?
+            .storage(<AccountStore<IndracoreRuntime> as Store<IndracoreRuntime>>::FIELD)?;
+        let change_sets = self.client().query_storage_at(&keys, Some(at)).await?;
+        let mut page = Vec::with_capacity(keys.len());
+        for change_set in change_sets {
+            for (key, data) in change_set.changes {
+                if let Some(data) = data {
+                    let info = SystemAccountInfo::decode(&mut &data.0[..])?;
+                    let account = AllAccount {
+                        accountid: storage.decode_map_key(&key)?,
+                        balance: self.token(info.data.free),
+                    };
+                    page.push((key, account));
+                }
+            }
+        }
+        page.sort_by(|(a, _), (b, _)| a.0.cmp(&b.0));
+        Ok(page)
+    }
+
+    pub async fn get_account_info(
+        &self,
+        account_id: impl IntoAccountId,
     ) -> Result<AccountInfo, Error> {
-        async_std::task::block_on(async move {
-            let client = match ClientBuilder::<IndracoreRuntime>::new()
-                .set_url(url)
-                .build()
-                .await
-            {
-                Ok(c) => c,
-                Err(e) => return Err(e),
-            };
-            let accountid32 = match IndraKeyring::accountid_from_str(account_id) {
-                Ok(acc) => acc,
-                Err(e) => return Err(e),
-            };
-            let info = match client.account(&accountid32, None).await {
-                Ok(i) => i,
-                Err(e) => return Err(e),
-            };
-            let data = BalanceInfo {
-                free: info.data.free,
-                reserved: info.data.reserved,
-                misc_frozen: info.data.misc_frozen,
-                fee_frozen: info.data.fee_frozen,
-            };
-            Ok(AccountInfo {
-                nonce: info.nonce,
-                refcount: info.refcount,
-                data,
+        let accountid32 = account_id.into_account_id(self.ss58_format())?;
+        let info = self.client().account(&accountid32, None).await?;
+        Ok(AccountInfo {
+            nonce: info.nonce,
+            refcount: info.refcount,
+            data: self.balance_info(&info.data),
+        })
+    }
+
+    /// Returns balances, locks and staking status of an account.
+    ///
+    /// Every value is read at block `at`, or at the current best block if `at` is `None`, so the
+    /// parts of the overview are consistent with each other.
+    pub async fn get_account_overview(
+        &self,
+        account_id: impl IntoAccountId,
+        at: Option<H256>,
+    ) -> Result<AccountOverview, Error> {
+        let accountid32 = account_id.into_account_id(self.ss58_format())?;
+        let at = match at {
+            Some(hash) => hash,
+            None => self
+                .client()
+                .block_hash(None)
+                .await?
+                .ok_or_else(|| Error::Other("Best block hash not found".into()))?,
+        };
+        let (info, locks, staking) = futures::try_join!(
+            self.client().account(&accountid32, Some(at)),
+            self.client().locks(&accountid32, Some(at)),
+            self.staking_info(&accountid32, at),
+        )?;
+
+        let frozen = info.data.misc_frozen.max(info.data.fee_frozen);
+        let locks = locks
+            .into_iter()
+            .map(|lock| LockInfo {
+                id: String::from_utf8_lossy(&lock.id).trim_end().to_string(),
+                amount: self.token(lock.amount),
+                reasons: lock.reasons,
             })
+            .collect();
+        Ok(AccountOverview {
+            at,
+            nonce: info.nonce,
+            refcount: info.refcount,
+            data: self.balance_info(&info.data),
+            locks,
+            transferable: self.token(info.data.free.saturating_sub(frozen)),
+            staking,
         })
     }
+
+    async fn staking_info(
+        &self,
+        account_id: &AccountId32,
+        at: H256,
+    ) -> Result<Option<StakingInfo>, Error> {
+        let client = self.client();
+        let (stash, controller) = match client.bonded(account_id.clone(), Some(at)).await? {
+            Some(controller) => (account_id.clone(), controller),
+            // not a stash, but it may be the controller of one
+            None => match client.ledger(account_id.clone(), Some(at)).await? {
+                Some(ledger) => (ledger.stash, account_id.clone()),
+                None => return Ok(None),
+            },
+        };
+        let (ledger, nominations) = futures::try_join!(
+            client.ledger(controller.clone(), Some(at)),
+            client.nominators(stash.clone(), Some(at)),
+        )?;
+        let ledger = match ledger {
+            Some(ledger) => ledger,
+            None => return Ok(None),
+        };
+        let unlocking = ledger
+            .unlocking
+            .iter()
+            .fold(0u128, |total, chunk| total.saturating_add(chunk.value));
+        Ok(Some(StakingInfo {
+            stash,
+            controller,
+            total: self.token(ledger.total),
+            active: self.token(ledger.active),
+            unlocking: self.token(unlocking),
+            nominations: nominations
+                .map(|nominations| nominations.targets)
+                .unwrap_or_default(),
+        }))
+    }
+
+    fn balance_info(&self, data: &AccountData<u128>) -> BalanceInfo {
+        BalanceInfo {
+            free: self.token(data.free),
+            reserved: self.token(data.reserved),
+            misc_frozen: self.token(data.misc_frozen),
+            fee_frozen: self.token(data.fee_frozen),
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    #[test]
-    fn test_fecth_all_account() {
-        assert!(Account::fecth_all_account("ws://127.0.0.1:9944").is_ok())
+    #[async_std::test]
+    async fn test_fecth_all_account() {
+        assert!(Account::fecth_all_account("ws://127.0.0.1:9944")
+            .await
+            .is_ok())
     }
 
-    #[test]
-    fn test_fecth_account_info() {
+    #[async_std::test]
+    async fn test_resume_account_stream() {
+        let indra = Indra::new("ws://127.0.0.1:9944").await.unwrap();
+        let export = AccountExport::new().set_page_size(2).set_concurrency(2);
+        let stream = indra.account_stream(export.clone()).await.unwrap();
+        let at = stream.at();
+        let all: Vec<_> = stream.try_collect().await.unwrap();
+
+        let (cursor, _) = all[0].clone();
+        let resumed: Vec<_> = indra
+            .account_stream(export.set_at(at).set_cursor(cursor))
+            .await
+            .unwrap()
+            .try_collect()
+            .await
+            .unwrap();
+        assert_eq!(&all[1..], &resumed[..]);
+    }
+
+    #[async_std::test]
+    async fn test_all_account_ids() {
+        let indra = Indra::new("ws://127.0.0.1:9944").await.unwrap();
+        let accounts = indra.fecth_all_account().await.unwrap();
+        let account = &accounts[0];
+        let free = indra.get_free_balance(&account.accountid).await.unwrap();
+        assert_eq!(account.balance, free);
+    }
+
+    #[async_std::test]
+    async fn test_fecth_account_info() {
         let account_id = "BauKu2iL4fncgfy22YSLGc1aDLpyuUUe5z8yNF2pDtLNr4E";
-        let info = Account::get_account_info("ws://127.0.0.1:9944", account_id);
+        let indra = Indra::new("ws://127.0.0.1:9944").await.unwrap();
+        let info = indra.get_account_info(account_id).await;
 
         let balance_info = BalanceInfo {
-            free: 0,
-            reserved: 0,
-            misc_frozen: 0,
-            fee_frozen: 0,
+            free: indra.token(0),
+            reserved: indra.token(0),
+            misc_frozen: indra.token(0),
+            fee_frozen: indra.token(0),
         };
         let test_info = AccountInfo {
             nonce: 0,
@@ -114,11 +428,24 @@ mod tests {
         assert_ne!(info.unwrap(), test_info);
     }
 
-    #[test]
-    fn test_get_info() {
+    #[async_std::test]
+    async fn test_get_info() {
+        let account_id = "BauKu2iL4fncgfy22YSLGc1aDLpyuUUe5z8yNF2pDtLNr4E";
+        let info = Account::get_account_info("ws://127.0.0.1:9944", account_id)
+            .await
+            .unwrap();
+
+        assert_ne!(info.data.free.planck(), 0);
+    }
+
+    #[async_std::test]
+    async fn test_get_account_overview() {
         let account_id = "BauKu2iL4fncgfy22YSLGc1aDLpyuUUe5z8yNF2pDtLNr4E";
-        let info = Account::get_account_info("ws://127.0.0.1:9944", account_id).unwrap();
+        let indra = Indra::new("ws://127.0.0.1:9944").await.unwrap();
+        let overview = indra.get_account_overview(account_id, None).await.unwrap();
+        let info = indra.get_account_info(account_id).await.unwrap();
 
-        assert_ne!(info.data.free, 0);
+        assert!(overview.transferable <= overview.data.free);
+        assert_eq!(overview.data.free.symbol(), info.data.free.symbol());
     }
 }
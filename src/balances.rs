@@ -1,65 +1,55 @@
-use crate::utils::keyring::IndraKeyring;
-use indracore_subxt::{balances::*, system::*, ClientBuilder, Error, IndracoreRuntime};
+use crate::{
+    indra::Indra,
+    utils::{keyring::IntoAccountId, token::TokenAmount},
+};
+use indracore_subxt::{balances::*, system::*, Error};
 
 pub struct Balance {}
 
 impl Balance {
-    pub fn get_total_issuance(url: &'static str) -> Result<u128, Error> {
-        async_std::task::block_on(async move {
-            let client = match ClientBuilder::<IndracoreRuntime>::new()
-                .set_url(url)
-                .build()
-                .await
-            {
-                Ok(c) => c,
-                Err(e) => return Err(e),
-            };
-            let total_issuance = match client.total_issuance(None).await {
-                Ok(t) => t,
-                Err(e) => return Err(e),
-            };
-            Ok(total_issuance)
-        })
+    pub async fn get_total_issuance(url: impl AsRef<str>) -> Result<TokenAmount, Error> {
+        Indra::new(url).await?.get_total_issuance().await
     }
 
-    pub fn get_free_balance(url: &'static str, account_id: &'static str) -> Result<u128, Error> {
-        async_std::task::block_on(async move {
-            let client = match ClientBuilder::<IndracoreRuntime>::new()
-                .set_url(url)
-                .build()
-                .await
-            {
-                Ok(c) => c,
-                Err(e) => return Err(e),
-            };
-            let accountid32 = match IndraKeyring::accountid_from_str(account_id) {
-                Ok(acc) => acc,
-                Err(e) => return Err(e),
-            };
-            let info = match client.account(&accountid32, None).await {
-                Ok(i) => i,
-                Err(e) => return Err(e),
-            };
-            Ok(info.data.free)
-        })
+    pub async fn get_free_balance(
+        url: impl AsRef<str>,
+        account_id: impl IntoAccountId,
+    ) -> Result<TokenAmount, Error> {
+        Indra::new(url).await?.get_free_balance(account_id).await
+    }
+}
+
+impl Indra {
+    pub async fn get_total_issuance(&self) -> Result<TokenAmount, Error> {
+        let total_issuance = self.client().total_issuance(None).await?;
+        Ok(self.token(total_issuance))
+    }
+
+    pub async fn get_free_balance(
+        &self,
+        account_id: impl IntoAccountId,
+    ) -> Result<TokenAmount, Error> {
+        let accountid32 = account_id.into_account_id(self.ss58_format())?;
+        let info = self.client().account(&accountid32, None).await?;
+        Ok(self.token(info.data.free))
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    #[test]
-    fn test_total_issuance() {
+    #[async_std::test]
+    async fn test_total_issuance() {
         let url = "ws://127.0.0.1:9944";
-        let total_issuance = Balance::get_total_issuance(url);
-        assert_ne!(total_issuance.unwrap(), 0)
+        let total_issuance = Balance::get_total_issuance(url).await;
+        assert_ne!(total_issuance.unwrap().planck(), 0)
     }
 
-    #[test]
-    fn test_state_read_free_balance() {
+    #[async_std::test]
+    async fn test_state_read_free_balance() {
         let account_id = "BauKu2iL4fncgfy22YSLGc1aDLpyuUUe5z8yNF2pDtLNr4E";
         let url = "ws://127.0.0.1:9944";
-        let balance = Balance::get_free_balance(url, account_id);
-        assert_ne!(balance.unwrap(), 0);
+        let balance = Balance::get_free_balance(url, account_id).await;
+        assert_ne!(balance.unwrap().planck(), 0);
     }
 }
@@ -0,0 +1,459 @@
+use crate::{
+    indra::{Indra, Signed},
+    transfer::{TransferReceipt, WaitFor},
+    utils::{address::IndraAddress, keyring::IndraKeyring, token::TokenAmount},
+};
+use futures::future::join_all;
+use indracore_subxt::{
+    balances::*, sp_core::H256, sp_runtime::generic::Era, utility::BatchAllCall, Encoded, Error,
+    IndracoreRuntime, Signer, SystemProperties,
+};
+use serde::Deserialize;
+use std::marker::PhantomData;
+
+/// How a [`BatchTransfer`] is submitted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BatchMode {
+    /// A single `utility.batch_all`, either every row is paid or none is.
+    BatchAll,
+    /// One transfer per row, signed with consecutive nonces and submitted in order.
+    Sequential,
+}
+
+impl Default for BatchMode {
+    fn default() -> Self {
+        BatchMode::BatchAll
+    }
+}
+
+/// A single payment of a batch.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BatchRow {
+    pub to: IndraAddress,
+    pub amount: TokenAmount,
+}
+
+/// The outcome of a single row of a batch.
+#[derive(Debug)]
+pub struct BatchResult {
+    pub row: BatchRow,
+    /// The receipt of the extrinsic that carried the row. For a `batch_all` every row shares
+    /// the block, fee and dispatch error of the batch.
+    pub result: Result<TransferReceipt, Error>,
+}
+
+#[derive(Debug)]
+pub struct BatchReport {
+    pub rows: Vec<BatchResult>,
+}
+
+impl BatchReport {
+    /// Returns `true` if every row was paid.
+    pub fn is_success(&self) -> bool {
+        self.rows.iter().all(|row| match &row.result {
+            Ok(receipt) => receipt.is_success(),
+            Err(_) => false,
+        })
+    }
+}
+
+#[derive(Deserialize)]
+struct ManifestRow {
+    address: String,
+    amount: serde_json::Value,
+}
+
+/// Payments of one sender to many recipients.
+#[derive(Debug, Clone)]
+pub struct BatchTransfer {
+    rows: Vec<BatchRow>,
+    mode: BatchMode,
+    keep_alive: bool,
+}
+
+impl BatchTransfer {
+    pub fn new(rows: Vec<BatchRow>) -> Self {
+        Self {
+            rows,
+            mode: BatchMode::default(),
+            keep_alive: false,
+        }
+    }
+
+    /// Reads `address,amount` rows, amounts in whole tokens such as `12.5`.
+    ///
+    /// Empty lines, lines starting with `#` and an `address,amount` header are skipped. Every
+    /// row is validated against the chain's address format and token, and all invalid rows
+    /// are reported in a single error.
+    pub fn from_csv(indra: &Indra, csv: impl AsRef<str>) -> Result<BatchTransfer, Error> {
+        Self::parse_csv(csv.as_ref(), indra.client().properties())
+    }
+
+    /// Reads a JSON array of `{"address": ..., "amount": ...}` objects, amounts in whole
+    /// tokens given as a string such as `"12.5"`.
+    ///
+    /// JSON numbers are rejected, they would be rounded to the precision of a float.
+    pub fn from_json(indra: &Indra, json: impl AsRef<str>) -> Result<BatchTransfer, Error> {
+        Self::parse_json(json.as_ref(), indra.client().properties())
+    }
+
+    fn parse_csv(csv: &str, properties: &SystemProperties) -> Result<BatchTransfer, Error> {
+        let mut rows = Vec::new();
+        let mut errors = Vec::new();
+        for (index, line) in csv.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+            if rows.is_empty()
+                && errors.is_empty()
+                && fields.len() == 2
+                && fields[0].eq_ignore_ascii_case("address")
+                && fields[1].eq_ignore_ascii_case("amount")
+            {
+                continue;
+            }
+            let row = match fields.as_slice() {
+                [address, amount] => Self::parse_row(properties, address, amount),
+                _ => Err(Error::Other("expected address,amount".into())),
+            };
+            match row {
+                Ok(row) => rows.push(row),
+                Err(err) => errors.push(format!("line {}: {}", index + 1, err)),
+            }
+        }
+        Self::validated(rows, errors)
+    }
+
+    fn parse_json(json: &str, properties: &SystemProperties) -> Result<BatchTransfer, Error> {
+        let manifest: Vec<ManifestRow> = serde_json::from_str(json)?;
+        let mut rows = Vec::new();
+        let mut errors = Vec::new();
+        for (index, row) in manifest.into_iter().enumerate() {
+            let row = match &row.amount {
+                serde_json::Value::String(amount) => {
+                    Self::parse_row(properties, &row.address, amount)
+                }
+                amount => Err(Error::Other(format!(
+                    "amount {} must be a string such as \"12.5\"",
+                    amount
+                ))),
+            };
+            match row {
+                Ok(row) => rows.push(row),
+                Err(err) => errors.push(format!("row {}: {}", index, err)),
+            }
+        }
+        Self::validated(rows, errors)
+    }
+
+    pub fn set_mode(mut self, mode: BatchMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Uses `transfer_keep_alive` for every row so the sender's account is never reaped.
+    pub fn set_keep_alive(mut self, keep_alive: bool) -> Self {
+        self.keep_alive = keep_alive;
+        self
+    }
+
+    pub fn rows(&self) -> &[BatchRow] {
+        &self.rows
+    }
+
+    /// Returns the sum of all rows, `None` on overflow or for an empty batch.
+    pub fn total(&self) -> Option<TokenAmount> {
+        let (first, rest) = self.rows.split_first()?;
+        rest.iter().try_fold(first.amount.clone(), |total, row| {
+            total.checked_add(&row.amount)
+        })
+    }
+
+    fn parse_row(
+        properties: &SystemProperties,
+        address: &str,
+        amount: &str,
+    ) -> Result<BatchRow, Error> {
+        Ok(BatchRow {
+            to: IndraAddress::parse(address, properties.ss58_format)?,
+            amount: TokenAmount::parse(
+                amount,
+                properties.token_decimals,
+                &properties.token_symbol,
+            )?,
+        })
+    }
+
+    fn validated(rows: Vec<BatchRow>, errors: Vec<String>) -> Result<BatchTransfer, Error> {
+        if !errors.is_empty() {
+            return Err(Error::Other(format!(
+                "Invalid batch rows: {}",
+                errors.join("; ")
+            )));
+        }
+        if rows.is_empty() {
+            return Err(Error::Other("Empty batch".into()));
+        }
+        Ok(Self::new(rows))
+    }
+}
+
+impl Indra {
+    /// Pays every row of `batch` from `from` and waits until the payments reach `wait`.
+    ///
+    /// The total plus the estimated fees is checked against the sender's spendable balance
+    /// before anything is submitted. A `batch_all` that does not make it into a block fails the
+    /// whole call, a sequential row that does not is reported in its own result.
+    pub async fn batch_transfer(
+        &self,
+        from: impl AsRef<str>,
+        batch: &BatchTransfer,
+        wait: WaitFor,
+    ) -> Result<BatchReport, Error> {
+        if batch.rows.is_empty() {
+            return Err(Error::Other("Empty batch".into()));
+        }
+        let from = IndraKeyring::from_str(from)?;
+        let signer = from.pairsingner();
+        let client = self.client();
+        let total = batch
+            .total()
+            .ok_or_else(|| Error::Other("Batch total overflows".into()))?;
+        let total = self.planck(&total.into())?;
+        let data = client.account(&from.accountid(), None).await?.data;
+        let spendable = data
+            .free
+            .saturating_sub(data.misc_frozen.max(data.fee_frozen));
+
+        match batch.mode {
+            BatchMode::BatchAll => {
+                let calls = batch
+                    .rows
+                    .iter()
+                    .map(|row| self.encode_row(row, batch.keep_alive))
+                    .collect::<Result<Vec<_>, _>>()?;
+                let call = BatchAllCall {
+                    _runtime: PhantomData,
+                    calls: &calls,
+                };
+                // the fee is estimated on the extrinsic submitted, its nonce is released on error
+                let signed = client.create_signed_reserved(call, &signer).await?;
+                let fee = client.query_info(&signed.0, None).await?.partial_fee;
+                self.ensure_spendable(total, fee, spendable)?;
+
+                let progress = self.watch_signed(signed).await?;
+                let inclusion = self
+                    .wait_for_inclusion(progress, &from.accountid(), wait)
+                    .await?;
+                let mut events = inclusion.transfers()?.into_iter();
+                let rows = batch
+                    .rows
+                    .iter()
+                    .map(|row| BatchResult {
+                        row: row.clone(),
+                        result: Ok(inclusion.receipt(events.next())),
+                    })
+                    .collect();
+                Ok(BatchReport { rows })
+            }
+            BatchMode::Sequential => {
+                // the rows share one era, so the checkpoint is fetched once
+                let (era, checkpoint) = client.era().await?;
+                // every row costs about the same, so one estimate covers the batch
+                // the nonce of the estimate is released to the first row
+                let (first, _) = self
+                    .sign_row(&batch.rows[0], batch.keep_alive, &signer, era, checkpoint)
+                    .await?;
+                let fee = client.query_info(&first, None).await?.partial_fee;
+                let fees = fee.saturating_mul(batch.rows.len() as u128);
+                self.ensure_spendable(total, fees, spendable)?;
+
+                // submit in nonce order, a rejected row releases its nonce to the next row
+                let mut submitted = Vec::new();
+                for row in &batch.rows {
+                    let signed = self
+                        .sign_row(row, batch.keep_alive, &signer, era, checkpoint)
+                        .await;
+                    let result = match signed {
+                        Ok(signed) => self.watch_signed(signed).await,
+                        Err(err) => Err(err),
+                    };
+                    submitted.push(result);
+                }
+
+                let sender = from.accountid();
+                let results = join_all(submitted.into_iter().map(|result| async move {
+                    let inclusion = self.wait_for_inclusion(result?, &sender, wait).await?;
+                    let event = inclusion.transfers()?.into_iter().next();
+                    Ok::<_, Error>(inclusion.receipt(event))
+                }))
+                .await;
+                let rows = batch
+                    .rows
+                    .iter()
+                    .zip(results)
+                    .map(|(row, result)| BatchResult {
+                        row: row.clone(),
+                        result,
+                    })
+                    .collect();
+                Ok(BatchReport { rows })
+            }
+        }
+    }
+
+    fn encode_row(&self, row: &BatchRow, keep_alive: bool) -> Result<Encoded, Error> {
+        let to = row.to.multi_address();
+        let amount = self.planck(&(&row.amount).into())?;
+        if keep_alive {
+            self.client()
+                .encode(TransferKeepAliveCall { to: &to, amount })
+        } else {
+            self.client().encode(TransferCall { to: &to, amount })
+        }
+    }
+
+    async fn sign_row(
+        &self,
+        row: &BatchRow,
+        keep_alive: bool,
+        signer: &(dyn Signer<IndracoreRuntime> + Send + Sync),
+        era: Era,
+        checkpoint: H256,
+    ) -> Result<Signed, Error> {
+        let to = row.to.multi_address();
+        let amount = self.planck(&(&row.amount).into())?;
+        let client = self.client();
+        if keep_alive {
+            let call = TransferKeepAliveCall { to: &to, amount };
+            client
+                .create_signed_with_era(call, signer, era, checkpoint)
+                .await
+        } else {
+            let call = TransferCall { to: &to, amount };
+            client
+                .create_signed_with_era(call, signer, era, checkpoint)
+                .await
+        }
+    }
+
+    fn ensure_spendable(&self, total: u128, fees: u128, spendable: u128) -> Result<(), Error> {
+        if total.saturating_add(fees) > spendable {
+            return Err(Error::Other(format!(
+                "Batch total {} plus fees {} exceeds the spendable balance {}",
+                self.token(total),
+                self.token(fees),
+                self.token(spendable)
+            )));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use indracore_subxt::sp_core::crypto::AccountId32;
+
+    fn properties() -> SystemProperties {
+        SystemProperties {
+            ss58_format: 1,
+            token_decimals: 12,
+            token_symbol: "SEL".into(),
+        }
+    }
+
+    fn address(seed: u8) -> IndraAddress {
+        IndraAddress::new(AccountId32::from([seed; 32]), 1)
+    }
+
+    #[test]
+    fn test_parse_csv() {
+        let csv = format!(
+            "address,amount\n{},1.5\n# comment\n\n{}, 0.000000000001 SEL\n",
+            address(1),
+            address(2)
+        );
+        let batch = BatchTransfer::parse_csv(&csv, &properties()).unwrap();
+        assert_eq!(batch.rows().len(), 2);
+        assert_eq!(batch.rows()[0].to, address(1));
+        assert_eq!(batch.rows()[0].amount.planck(), 1_500_000_000_000);
+        assert_eq!(batch.rows()[1].amount.planck(), 1);
+
+        // an address of another network and an amount of another token are both reported
+        let other_network = IndraAddress::new(AccountId32::from([1u8; 32]), 42);
+        let invalid = format!(
+            "{},1\n{},1 DOT\n{}\n",
+            other_network,
+            address(2),
+            address(3)
+        );
+        let err = BatchTransfer::parse_csv(&invalid, &properties())
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("line 1") && err.contains("line 2") && err.contains("line 3"));
+
+        assert!(BatchTransfer::parse_csv("address,amount\n", &properties()).is_err());
+    }
+
+    #[test]
+    fn test_parse_json() {
+        let json = format!(
+            r#"[{{"address": "{}", "amount": "0.1"}}, {{"address": "{}", "amount": "2"}}]"#,
+            address(1),
+            address(2)
+        );
+        let batch = BatchTransfer::parse_json(&json, &properties()).unwrap();
+        assert_eq!(batch.total().unwrap().planck(), 2_100_000_000_000);
+
+        // a float cannot hold every amount, so numbers are rejected
+        let json = format!(
+            r#"[{{"address": "{}", "amount": 0.1}}, {{"address": "{}", "amount": null}}]"#,
+            address(1),
+            address(2)
+        );
+        let err = BatchTransfer::parse_json(&json, &properties())
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("row 0") && err.contains("row 1"));
+    }
+
+    #[async_std::test]
+    async fn test_batch_from_csv() {
+        let indra = Indra::new("ws://127.0.0.1:9944").await.unwrap();
+        let first = indra.address(AccountId32::from([1u8; 32]));
+        let second = indra.address(AccountId32::from([2u8; 32]));
+        let csv = format!(
+            "address,amount\n{},1.5\n# comment\n\n{},0.25\n",
+            first, second
+        );
+        let batch = BatchTransfer::from_csv(&indra, csv).unwrap();
+        assert_eq!(batch.rows().len(), 2);
+        assert_eq!(batch.rows()[0].to, first);
+        assert_eq!(batch.total().unwrap(), indra.parse_amount("1.75").unwrap());
+
+        let invalid = format!("not-an-address,1\n{},x\n", second);
+        let err = BatchTransfer::from_csv(&indra, invalid).unwrap_err();
+        assert!(err.to_string().contains("line 1"));
+        assert!(err.to_string().contains("line 2"));
+    }
+
+    #[async_std::test]
+    async fn test_batch_transfer() {
+        let indra = Indra::new("ws://127.0.0.1:9944").await.unwrap();
+        let json = format!(
+            r#"[{{"address": "{}", "amount": "0.01"}}, {{"address": "{}", "amount": "0.02"}}]"#,
+            indra.address(AccountId32::from([1u8; 32])),
+            indra.address(AccountId32::from([2u8; 32])),
+        );
+        let batch = BatchTransfer::from_json(&indra, json).unwrap();
+        let report = indra
+            .batch_transfer("//Bob", &batch, WaitFor::InBlock)
+            .await
+            .unwrap();
+        assert!(report.is_success());
+        assert_eq!(report.rows.len(), 2);
+    }
+}
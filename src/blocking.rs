@@ -0,0 +1,250 @@
+//! Blocking versions of the facade operations.
+//!
+//! Every call drives the async API to completion on the current thread, so these must not be
+//! used from inside an async executor.
+
+use crate::{
+    account::{AccountInfo, AccountOverview, AllAccount},
+    batch::{BatchReport, BatchTransfer},
+    indra,
+    transfer::{TransferMode, TransferReceipt, WaitFor},
+    utils::{
+        address::IndraAddress,
+        keyring::IntoAccountId,
+        token::{Amount, TokenAmount},
+    },
+};
+use async_std::task::block_on;
+use indracore_subxt::{
+    sp_core::{crypto::AccountId32, H256},
+    Error,
+};
+
+/// Blocking counterpart of [`indra::Indra`].
+#[derive(Clone)]
+pub struct Indra {
+    inner: indra::Indra,
+}
+
+impl Indra {
+    pub fn new(url: impl AsRef<str>) -> Result<Indra, Error> {
+        let inner = block_on(indra::Indra::new(url))?;
+        Ok(Self { inner })
+    }
+
+    /// Returns the async handle sharing this connection.
+    pub fn inner(&self) -> &indra::Indra {
+        &self.inner
+    }
+
+    pub fn parse_address(&self, address: impl AsRef<str>) -> Result<IndraAddress, Error> {
+        self.inner.parse_address(address)
+    }
+
+    pub fn address(&self, account_id: AccountId32) -> IndraAddress {
+        self.inner.address(account_id)
+    }
+
+    pub fn token(&self, planck: u128) -> TokenAmount {
+        self.inner.token(planck)
+    }
+
+    pub fn parse_amount(&self, amount: impl AsRef<str>) -> Result<TokenAmount, Error> {
+        self.inner.parse_amount(amount)
+    }
+
+    pub fn fecth_all_account(&self) -> Result<Vec<AllAccount>, Error> {
+        block_on(self.inner.fecth_all_account())
+    }
+
+    pub fn get_account_info(&self, account_id: impl IntoAccountId) -> Result<AccountInfo, Error> {
+        block_on(self.inner.get_account_info(account_id))
+    }
+
+    pub fn get_account_overview(
+        &self,
+        account_id: impl IntoAccountId,
+        at: Option<H256>,
+    ) -> Result<AccountOverview, Error> {
+        block_on(self.inner.get_account_overview(account_id, at))
+    }
+
+    pub fn get_total_issuance(&self) -> Result<TokenAmount, Error> {
+        block_on(self.inner.get_total_issuance())
+    }
+
+    pub fn get_free_balance(&self, account_id: impl IntoAccountId) -> Result<TokenAmount, Error> {
+        block_on(self.inner.get_free_balance(account_id))
+    }
+
+    pub fn transfer(
+        &self,
+        from: impl AsRef<str>,
+        to: impl IntoAccountId,
+        amount: impl Into<Amount>,
+    ) -> Result<H256, Error> {
+        block_on(self.inner.transfer(from, to, amount))
+    }
+
+    pub fn transfer_and_watch(
+        &self,
+        from: impl AsRef<str>,
+        to: impl IntoAccountId,
+        amount: impl Into<Amount>,
+        wait: WaitFor,
+    ) -> Result<TransferReceipt, Error> {
+        block_on(self.inner.transfer_and_watch(from, to, amount, wait))
+    }
+
+    pub fn transfer_keep_alive(
+        &self,
+        from: impl AsRef<str>,
+        to: impl IntoAccountId,
+        amount: impl Into<Amount>,
+    ) -> Result<H256, Error> {
+        block_on(self.inner.transfer_keep_alive(from, to, amount))
+    }
+
+    pub fn transfer_all(
+        &self,
+        from: impl AsRef<str>,
+        to: impl IntoAccountId,
+        keep_alive: bool,
+    ) -> Result<H256, Error> {
+        block_on(self.inner.transfer_all(from, to, keep_alive))
+    }
+
+    pub fn max_transferable(
+        &self,
+        from: impl AsRef<str>,
+        to: impl IntoAccountId,
+        keep_alive: bool,
+    ) -> Result<TokenAmount, Error> {
+        block_on(self.inner.max_transferable(from, to, keep_alive))
+    }
+
+    pub fn force_transfer(
+        &self,
+        sudo: impl AsRef<str>,
+        source: impl IntoAccountId,
+        to: impl IntoAccountId,
+        amount: impl Into<Amount>,
+    ) -> Result<H256, Error> {
+        block_on(self.inner.force_transfer(sudo, source, to, amount))
+    }
+
+    pub fn set_balance(
+        &self,
+        sudo: impl AsRef<str>,
+        who: impl IntoAccountId,
+        free: impl Into<Amount>,
+        reserved: impl Into<Amount>,
+    ) -> Result<H256, Error> {
+        block_on(self.inner.set_balance(sudo, who, free, reserved))
+    }
+
+    pub fn batch_transfer(
+        &self,
+        from: impl AsRef<str>,
+        batch: &BatchTransfer,
+        wait: WaitFor,
+    ) -> Result<BatchReport, Error> {
+        block_on(self.inner.batch_transfer(from, batch, wait))
+    }
+}
+
+pub struct Account {}
+
+impl Account {
+    pub fn fecth_all_account(url: impl AsRef<str>) -> Result<Vec<AllAccount>, Error> {
+        block_on(crate::account::Account::fecth_all_account(url))
+    }
+
+    pub fn get_account_info(
+        url: impl AsRef<str>,
+        account_id: impl IntoAccountId,
+    ) -> Result<AccountInfo, Error> {
+        block_on(crate::account::Account::get_account_info(url, account_id))
+    }
+
+    pub fn get_account_overview(
+        url: impl AsRef<str>,
+        account_id: impl IntoAccountId,
+    ) -> Result<AccountOverview, Error> {
+        block_on(crate::account::Account::get_account_overview(
+            url, account_id,
+        ))
+    }
+}
+
+pub struct Balance {}
+
+impl Balance {
+    pub fn get_total_issuance(url: impl AsRef<str>) -> Result<TokenAmount, Error> {
+        block_on(crate::balances::Balance::get_total_issuance(url))
+    }
+
+    pub fn get_free_balance(
+        url: impl AsRef<str>,
+        account_id: impl IntoAccountId,
+    ) -> Result<TokenAmount, Error> {
+        block_on(crate::balances::Balance::get_free_balance(url, account_id))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Transfer {
+    inner: crate::transfer::Transfer,
+}
+
+impl Transfer {
+    pub fn new(
+        url: impl Into<String>,
+        from: impl Into<String>,
+        to: impl Into<String>,
+        amount: impl Into<Amount>,
+    ) -> Self {
+        Self {
+            inner: crate::transfer::Transfer::new(url, from, to, amount),
+        }
+    }
+
+    pub fn set_mode(self, mode: TransferMode) -> Self {
+        Self {
+            inner: self.inner.set_mode(mode),
+        }
+    }
+
+    pub fn set_tip(self, tip: impl Into<Amount>) -> Self {
+        Self {
+            inner: self.inner.set_tip(tip),
+        }
+    }
+
+    pub fn transfer(&self) -> Result<H256, Error> {
+        block_on(self.inner.transfer())
+    }
+
+    pub fn estimate_fee(&self) -> Result<TokenAmount, Error> {
+        block_on(self.inner.estimate_fee())
+    }
+
+    pub fn transfer_and_watch(&self, wait: WaitFor) -> Result<TransferReceipt, Error> {
+        block_on(self.inner.transfer_and_watch(wait))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_blocking_get_info() {
+        let account_id = "BauKu2iL4fncgfy22YSLGc1aDLpyuUUe5z8yNF2pDtLNr4E";
+        let indra = Indra::new("ws://127.0.0.1:9944").unwrap();
+        let info = indra.get_account_info(account_id).unwrap();
+        let free = Balance::get_free_balance("ws://127.0.0.1:9944", account_id).unwrap();
+
+        assert_eq!(info.data.free, free);
+    }
+}
@@ -0,0 +1,240 @@
+use crate::utils::{
+    address::IndraAddress,
+    token::{Amount, TokenAmount},
+};
+use indracore_subxt::{
+    sp_core::{crypto::AccountId32, H256},
+    BlockEvents, Client, ClientBuilder, Error, EventsDecoder, IndracoreRuntime, NonceReservation,
+    TransactionProgress, UncheckedExtrinsic,
+};
+use std::sync::Arc;
+
+/// A connection to an Indracore node shared by every facade operation.
+///
+/// Metadata, genesis hash, runtime version and system properties are fetched once when
+/// connecting. Cloning only bumps a reference count, so one handle can be handed to every
+/// task of a service.
+#[derive(Clone)]
+pub struct Indra {
+    client: Arc<Client<IndracoreRuntime>>,
+}
+
+impl Indra {
+    pub async fn new(url: impl AsRef<str>) -> Result<Indra, Error> {
+        let client = ClientBuilder::<IndracoreRuntime>::new()
+            .set_url(url.as_ref())
+            .build()
+            .await?;
+        Ok(Self::from_client(client))
+    }
+
+    pub fn from_client(client: Client<IndracoreRuntime>) -> Indra {
+        Self {
+            client: Arc::new(client),
+        }
+    }
+
+    pub fn client(&self) -> &Client<IndracoreRuntime> {
+        &self.client
+    }
+
+    /// Returns the SS58 network prefix of the connected chain.
+    pub fn ss58_format(&self) -> u8 {
+        self.client.properties().ss58_format
+    }
+
+    /// Parses an address, rejecting addresses encoded for another network.
+    pub fn parse_address(&self, address: impl AsRef<str>) -> Result<IndraAddress, Error> {
+        IndraAddress::parse(address, self.ss58_format())
+    }
+
+    /// Returns the address of `account_id` in the chain's format.
+    pub fn address(&self, account_id: AccountId32) -> IndraAddress {
+        IndraAddress::new(account_id, self.ss58_format())
+    }
+
+    /// Returns `planck` denominated in the chain's native token.
+    pub fn token(&self, planck: u128) -> TokenAmount {
+        TokenAmount::with_properties(planck, self.client.properties())
+    }
+
+    /// Parses an amount of the chain's native token such as `"12.5 SEL"`.
+    pub fn parse_amount(&self, amount: impl AsRef<str>) -> Result<TokenAmount, Error> {
+        let properties = self.client.properties();
+        TokenAmount::parse(amount, properties.token_decimals, &properties.token_symbol)
+    }
+
+    /// Returns `amount` in planck, rejecting amounts of another token.
+    pub fn planck(&self, amount: &Amount) -> Result<u128, Error> {
+        amount.to_planck(self.client.properties())
+    }
+
+    /// Returns a decoder for the events of every module of the runtime.
+    pub(crate) fn events_decoder(&self) -> EventsDecoder<IndracoreRuntime> {
+        self.client.runtime_events_decoder()
+    }
+
+    /// Returns every event of a block, grouped by phase through [`BlockEvents`].
+    pub async fn events_at(
+        &self,
+        block_hash: H256,
+    ) -> Result<BlockEvents<IndracoreRuntime>, Error> {
+        self.client.events_at(block_hash).await
+    }
+
+    /// Submits a signed extrinsic, settling its reserved nonce with the outcome.
+    pub(crate) async fn submit_signed(&self, signed: Signed) -> Result<H256, Error> {
+        let (extrinsic, reservation) = signed;
+        let result = self.client.submit_extrinsic(extrinsic).await;
+        if let Some(reservation) = reservation {
+            reservation.settle(&result);
+        }
+        result
+    }
+
+    /// Submits a signed extrinsic and follows its progress, which keeps its reserved nonce in
+    /// flight until it is in a block.
+    pub(crate) async fn watch_signed(
+        &self,
+        signed: Signed,
+    ) -> Result<TransactionProgress<IndracoreRuntime>, Error> {
+        let (extrinsic, reservation) = signed;
+        let result = self
+            .client
+            .watch_progress(extrinsic, self.events_decoder())
+            .await;
+        match (result, reservation) {
+            (Ok(progress), Some(reservation)) => Ok(progress.track_nonce(reservation)),
+            (Ok(progress), None) => Ok(progress),
+            (Err(err), reservation) => {
+                if let Some(reservation) = reservation {
+                    reservation.reject(&err);
+                }
+                Err(err)
+            }
+        }
+    }
+}
+
+/// A signed extrinsic with the nonce reserved for it, see [`Client::create_signed_reserved`].
+pub(crate) type Signed = (
+    UncheckedExtrinsic<IndracoreRuntime>,
+    Option<NonceReservation<IndracoreRuntime>>,
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        batch::{BatchRow, BatchTransfer},
+        transfer::WaitFor,
+        utils::keyring::IndraKeyring,
+    };
+    use futures::StreamExt;
+    use indracore_subxt::{
+        balances::{TransferCall, TransferEvent},
+        sp_runtime::MultiAddress,
+        Raw, TransactionStatus,
+    };
+
+    async fn progress(indra: &Indra, from: &str) -> TransactionProgress<IndracoreRuntime> {
+        let signer = IndraKeyring::from_str(from).unwrap().pairsingner();
+        let dest = MultiAddress::from(AccountId32::from([2u8; 32]));
+        let call = TransferCall {
+            to: &dest,
+            amount: 10_000_000_000,
+        };
+        let client = indra.client();
+        let extrinsic = client.create_signed(call, &signer).await.unwrap();
+        client
+            .watch_progress(extrinsic, indra.events_decoder())
+            .await
+            .unwrap()
+    }
+
+    #[async_std::test]
+    async fn test_reuse_connection() {
+        let indra = Indra::new("ws://127.0.0.1:9944").await.unwrap();
+        let account_id = "BauKu2iL4fncgfy22YSLGc1aDLpyuUUe5z8yNF2pDtLNr4E";
+
+        let shared = indra.clone();
+        let info = shared.get_account_info(account_id).await.unwrap();
+        let free = indra.get_free_balance(account_id).await.unwrap();
+        assert_eq!(info.data.free, free);
+    }
+
+    #[async_std::test]
+    async fn test_address_roundtrip() {
+        let indra = Indra::new("ws://127.0.0.1:9944").await.unwrap();
+        let address = indra.address(AccountId32::from([1u8; 32]));
+        assert_eq!(indra.parse_address(address.to_string()).unwrap(), address);
+    }
+
+    #[async_std::test]
+    async fn test_events_at() {
+        let indra = Indra::new("ws://127.0.0.1:9944").await.unwrap();
+        let block_hash = indra.client().block_hash(None).await.unwrap().unwrap();
+        let events = indra.events_at(block_hash).await.unwrap();
+        assert_eq!(events.block, block_hash);
+        // the timestamp inherent is the first extrinsic of every block
+        assert!(events.extrinsic(0).next().is_some());
+        assert!(events.by_extrinsic().contains_key(&0));
+    }
+
+    #[async_std::test]
+    async fn test_events_at_batch() {
+        let indra = Indra::new("ws://127.0.0.1:9944").await.unwrap();
+        let rows = vec![1u8, 2]
+            .into_iter()
+            .map(|seed| BatchRow {
+                to: indra.address(AccountId32::from([seed; 32])),
+                amount: indra.parse_amount("0.01").unwrap(),
+            })
+            .collect();
+        let report = indra
+            .batch_transfer("//Eve//stash", &BatchTransfer::new(rows), WaitFor::InBlock)
+            .await
+            .unwrap();
+        assert!(report.is_success());
+        let receipt = report.rows[0].result.as_ref().unwrap();
+
+        let events = indra.events_at(receipt.block_hash).await.unwrap();
+        let events: Vec<_> = events.extrinsic(receipt.extrinsic_index).collect();
+        let count = |module: &str, variant: &str| {
+            events
+                .iter()
+                .filter(|raw| match raw {
+                    Raw::Event(event) => event.module == module && event.variant == variant,
+                    Raw::Error(_) => false,
+                })
+                .count()
+        };
+        assert_eq!(count("Utility", "BatchCompleted"), 1);
+        assert_eq!(count("Balances", "Transfer"), 2);
+    }
+
+    #[async_std::test]
+    async fn test_progress_stream() {
+        let indra = Indra::new("ws://127.0.0.1:9944").await.unwrap();
+        let statuses: Vec<_> = progress(&indra, "//Eve").await.collect().await;
+        // the stream ends after the final status
+        assert!(matches!(
+            statuses.last(),
+            Some(TransactionStatus::Finalized(_))
+        ));
+        assert!(statuses
+            .iter()
+            .any(|status| matches!(status, TransactionStatus::InBlock(_))));
+    }
+
+    #[async_std::test]
+    async fn test_progress_wait_for_in_block() {
+        let indra = Indra::new("ws://127.0.0.1:9944").await.unwrap();
+        let mut progress = progress(&indra, "//Ferdie").await;
+        // statuses taken before waiting are not seen again
+        assert!(progress.next().await.is_some());
+        let success = progress.wait_for_in_block().await.unwrap();
+        let event = success.find_event::<TransferEvent<_>>().unwrap().unwrap();
+        assert_eq!(event.to, AccountId32::from([2u8; 32]));
+    }
+}
@@ -1,4 +1,8 @@
 pub mod account;
 pub mod balances;
+pub mod batch;
+#[cfg(feature = "blocking")]
+pub mod blocking;
+pub mod indra;
 pub mod transfer;
 pub mod utils;
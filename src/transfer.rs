@@ -1,65 +1,693 @@
-use crate::utils::keyring::IndraKeyring;
-use indracore_subxt::{balances::*, sp_core::H256, ClientBuilder, Error, IndracoreRuntime};
+use crate::{
+    indra::{Indra, Signed},
+    utils::{
+        keyring::{IndraKeyring, IntoAccountId},
+        token::{Amount, TokenAmount},
+    },
+};
+use codec::Decode;
+use indracore_subxt::{
+    balances::*,
+    sp_core::{crypto::AccountId32, H256},
+    sp_runtime::MultiAddress,
+    sudo::{SudoCall, SudoCallExt},
+    system::Phase,
+    Error, IndracoreRuntime, Raw, RawEvent, RuntimeError, TransactionError, TransactionProgress,
+    TransactionStatus,
+};
+use std::marker::PhantomData;
 
-#[derive(Debug)]
+/// How a [`Transfer`] moves funds.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TransferMode {
+    /// `balances.transfer`, the sender's account is reaped if it drops below the existential
+    /// deposit.
+    AllowDeath,
+    /// `balances.transfer_keep_alive`, fails rather than reaping the sender's account.
+    KeepAlive,
+    /// Sends everything the sender can spend after fees, the amount is ignored. With
+    /// `keep_alive` the existential deposit stays behind.
+    All { keep_alive: bool },
+    /// Root-only `balances.force_transfer` out of `source`, signed by the sudo key.
+    Force { source: AccountId32 },
+}
+
+impl Default for TransferMode {
+    fn default() -> Self {
+        TransferMode::AllowDeath
+    }
+}
+
+/// Signer settings of a transfer, a nonce set here is used instead of reserving one.
+#[derive(Debug, Clone, Copy, Default)]
+struct TransferSigner {
+    tip: Option<u128>,
+    nonce: Option<u32>,
+}
+
+/// The pool status a watched transfer waits for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WaitFor {
+    InBlock,
+    Finalized,
+}
+
+/// The outcome of a transfer included in a block.
+#[derive(Debug, Clone)]
+pub struct TransferReceipt {
+    pub extrinsic: H256,
+    pub block_hash: H256,
+    pub block_number: u32,
+    pub extrinsic_index: u32,
+    /// The `Balances.Transfer` event, `None` if the transfer failed.
+    pub event: Option<TransferEvent<IndracoreRuntime>>,
+    /// The fee and tip paid for the extrinsic even if it failed, as deposited to the
+    /// treasury and the block author.
+    pub fee: TokenAmount,
+    /// The dispatch error of a failed transfer.
+    pub error: Option<RuntimeError>,
+}
+
+impl TransferReceipt {
+    pub fn is_success(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct Transfer {
-    url: &'static str,
-    from: &'static str,
-    to: &'static str,
-    amount: u128,
+    url: String,
+    from: String,
+    to: String,
+    amount: Amount,
+    mode: TransferMode,
+    tip: Option<Amount>,
 }
 
 impl Transfer {
-    pub fn new(url: &'static str, from: &'static str, to: &'static str, amount: u128) -> Self {
+    pub fn new(
+        url: impl Into<String>,
+        from: impl Into<String>,
+        to: impl Into<String>,
+        amount: impl Into<Amount>,
+    ) -> Self {
         Self {
-            url,
-            from,
-            to,
-            amount,
+            url: url.into(),
+            from: from.into(),
+            to: to.into(),
+            amount: amount.into(),
+            mode: TransferMode::default(),
+            tip: None,
+        }
+    }
+
+    pub fn set_mode(mut self, mode: TransferMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Tips the block author to prioritise the transfer when blocks are full.
+    pub fn set_tip(mut self, tip: impl Into<Amount>) -> Self {
+        self.tip = Some(tip.into());
+        self
+    }
+
+    pub async fn transfer(&self) -> Result<H256, Error> {
+        self.submit(&Indra::new(&self.url).await?).await
+    }
+
+    /// Submits the transfer and waits until it is in a block or finalized.
+    pub async fn transfer_and_watch(&self, wait: WaitFor) -> Result<TransferReceipt, Error> {
+        self.submit_and_watch(&Indra::new(&self.url).await?, wait)
+            .await
+    }
+
+    /// Submits the transfer over an existing connection.
+    pub async fn submit(&self, indra: &Indra) -> Result<H256, Error> {
+        let extrinsic = self.create(indra, None).await?;
+        indra.submit_signed(extrinsic).await
+    }
+
+    /// Submits the transfer over an existing connection and waits until it is in a block or
+    /// finalized.
+    pub async fn submit_and_watch(
+        &self,
+        indra: &Indra,
+        wait: WaitFor,
+    ) -> Result<TransferReceipt, Error> {
+        let extrinsic = self.create(indra, None).await?;
+        indra.watch_transfer(extrinsic, wait).await
+    }
+
+    /// Estimates what the sender pays for the transfer, the partial fee plus the tip.
+    pub async fn estimate_fee(&self) -> Result<TokenAmount, Error> {
+        self.query_fee(&Indra::new(&self.url).await?).await
+    }
+
+    /// Estimates what the sender pays for the transfer over an existing connection.
+    pub async fn query_fee(&self, indra: &Indra) -> Result<TokenAmount, Error> {
+        let from = IndraKeyring::from_str(&self.from)?.accountid();
+        let nonce = indra.client().account_next_index(&from).await?;
+        let (extrinsic, _) = self.create(indra, Some(nonce)).await?;
+        let fee = indra
+            .client()
+            .query_info(&extrinsic, None)
+            .await?
+            .partial_fee;
+        Ok(indra.token(fee.saturating_add(self.tip(indra)?.unwrap_or_default())))
+    }
+
+    async fn create(&self, indra: &Indra, nonce: Option<u32>) -> Result<Signed, Error> {
+        let signer = TransferSigner {
+            tip: self.tip(indra)?,
+            nonce,
+        };
+        indra
+            .create_transfer(
+                &self.from,
+                &self.to,
+                self.amount.clone(),
+                &self.mode,
+                signer,
+            )
+            .await
+    }
+
+    fn tip(&self, indra: &Indra) -> Result<Option<u128>, Error> {
+        match &self.tip {
+            Some(tip) => Ok(Some(indra.planck(tip)?)),
+            None => Ok(None),
         }
     }
+}
+
+impl Indra {
+    pub async fn transfer(
+        &self,
+        from: impl AsRef<str>,
+        to: impl IntoAccountId,
+        amount: impl Into<Amount>,
+    ) -> Result<H256, Error> {
+        let extrinsic = self
+            .create_transfer(
+                from,
+                to,
+                amount,
+                &TransferMode::AllowDeath,
+                Default::default(),
+            )
+            .await?;
+        self.submit_signed(extrinsic).await
+    }
+
+    /// Transfers `amount` and waits until the transfer is in a block or finalized.
+    pub async fn transfer_and_watch(
+        &self,
+        from: impl AsRef<str>,
+        to: impl IntoAccountId,
+        amount: impl Into<Amount>,
+        wait: WaitFor,
+    ) -> Result<TransferReceipt, Error> {
+        let extrinsic = self
+            .create_transfer(
+                from,
+                to,
+                amount,
+                &TransferMode::AllowDeath,
+                Default::default(),
+            )
+            .await?;
+        self.watch_transfer(extrinsic, wait).await
+    }
+
+    /// Transfers `amount`, failing instead of reaping the sender's account.
+    pub async fn transfer_keep_alive(
+        &self,
+        from: impl AsRef<str>,
+        to: impl IntoAccountId,
+        amount: impl Into<Amount>,
+    ) -> Result<H256, Error> {
+        let extrinsic = self
+            .create_transfer(
+                from,
+                to,
+                amount,
+                &TransferMode::KeepAlive,
+                Default::default(),
+            )
+            .await?;
+        self.submit_signed(extrinsic).await
+    }
 
-    pub fn transfer(&self) -> Result<H256, Error> {
-        async_std::task::block_on(async move {
-            let client = match ClientBuilder::<IndracoreRuntime>::new()
-                .set_url(self.url)
-                .build()
-                .await
-            {
-                Ok(c) => c,
-                Err(e) => return Err(e),
+    /// Transfers everything `from` can spend after fees, see [`Indra::max_transferable`].
+    pub async fn transfer_all(
+        &self,
+        from: impl AsRef<str>,
+        to: impl IntoAccountId,
+        keep_alive: bool,
+    ) -> Result<H256, Error> {
+        let mode = TransferMode::All { keep_alive };
+        let extrinsic = self
+            .create_transfer(from, to, 0u128, &mode, Default::default())
+            .await?;
+        self.submit_signed(extrinsic).await
+    }
+
+    /// Returns the largest amount `from` can send to `to` in a single transfer.
+    ///
+    /// This is the free balance minus frozen funds and the fee of the transfer itself, and
+    /// with `keep_alive` also minus the existential deposit.
+    pub async fn max_transferable(
+        &self,
+        from: impl AsRef<str>,
+        to: impl IntoAccountId,
+        keep_alive: bool,
+    ) -> Result<TokenAmount, Error> {
+        let to = to.into_account_id(self.ss58_format())?;
+        let max = self
+            .max_transferable_with_tip(from, &to, keep_alive, None)
+            .await?;
+        Ok(self.token(max))
+    }
+
+    /// Returns the largest amount `from` can send to `to` while paying `tip`, with the fee
+    /// estimated for the transfer signed with that tip.
+    async fn max_transferable_with_tip(
+        &self,
+        from: impl AsRef<str>,
+        to: &AccountId32,
+        keep_alive: bool,
+        tip: Option<u128>,
+    ) -> Result<u128, Error> {
+        let from = IndraKeyring::from_str(from)?;
+        let mut signer = from.pairsingner();
+        if let Some(tip) = tip {
+            signer.set_tip(tip);
+        }
+        let dest = MultiAddress::from(to.clone());
+        let account = self.client().account(&from.accountid(), None).await?;
+        let data = account.data;
+        let frozen = data.misc_frozen.max(data.fee_frozen);
+        let mut spendable = data.free.saturating_sub(frozen);
+        if keep_alive {
+            let existential_deposit = self
+                .client()
+                .metadata()
+                .module("Balances")?
+                .constant("ExistentialDeposit")?
+                .value::<u128>()?;
+            spendable = spendable.saturating_sub(existential_deposit);
+        }
+
+        // the fee depends on the encoded length, which is largest for the largest amount
+        signer.set_nonce(account.nonce);
+        let client = self.client();
+        let info = if keep_alive {
+            let call = TransferKeepAliveCall {
+                to: &dest,
+                amount: spendable,
             };
-            let from = match IndraKeyring::from_str(self.from) {
-                Ok(acc) => acc,
-                Err(e) => return Err(e),
+            client.estimate_fee(call, &signer).await?
+        } else {
+            let call = TransferCall {
+                to: &dest,
+                amount: spendable,
             };
+            client.estimate_fee(call, &signer).await?
+        };
+        let fees = info.partial_fee.saturating_add(tip.unwrap_or_default());
+        Ok(spendable.saturating_sub(fees))
+    }
+
+    /// Moves `amount` out of `source` with the root-only `force_transfer`, signed by `sudo`.
+    pub async fn force_transfer(
+        &self,
+        sudo: impl AsRef<str>,
+        source: impl IntoAccountId,
+        to: impl IntoAccountId,
+        amount: impl Into<Amount>,
+    ) -> Result<H256, Error> {
+        let mode = TransferMode::Force {
+            source: source.into_account_id(self.ss58_format())?,
+        };
+        let extrinsic = self
+            .create_transfer(sudo, to, amount, &mode, Default::default())
+            .await?;
+        self.submit_signed(extrinsic).await
+    }
 
-            let signer = from.pairsingner();
+    /// Overwrites the free and reserved balance of `who` with the root-only `set_balance`,
+    /// signed by `sudo`.
+    pub async fn set_balance(
+        &self,
+        sudo: impl AsRef<str>,
+        who: impl IntoAccountId,
+        free: impl Into<Amount>,
+        reserved: impl Into<Amount>,
+    ) -> Result<H256, Error> {
+        let new_free = self.planck(&free.into())?;
+        let new_reserved = self.planck(&reserved.into())?;
+        let sudo = IndraKeyring::from_str(sudo)?;
+        let signer = sudo.pairsingner();
+        let who = MultiAddress::from(who.into_account_id(self.ss58_format())?);
+        let call = self.client().encode(SetBalanceCall {
+            who: &who,
+            new_free,
+            new_reserved,
+        })?;
+        self.client().sudo(&signer, &call).await
+    }
 
-            let dest = match IndraKeyring::multi_address_from_str(self.to) {
-                Ok(to) => to,
-                Err(e) => return Err(e),
-            };
+    /// Signs the extrinsic of a transfer in `mode`, `from` is the sudo key for a forced
+    /// transfer.
+    async fn create_transfer(
+        &self,
+        from: impl AsRef<str>,
+        to: impl IntoAccountId,
+        amount: impl Into<Amount>,
+        mode: &TransferMode,
+        options: TransferSigner,
+    ) -> Result<Signed, Error> {
+        let from = from.as_ref();
+        let mut signer = IndraKeyring::from_str(from)?.pairsingner();
+        if let Some(tip) = options.tip {
+            signer.set_tip(tip);
+        }
+        if let Some(nonce) = options.nonce {
+            signer.set_nonce(nonce);
+        }
+        let tip = options.tip;
+        let to = to.into_account_id(self.ss58_format())?;
+        let amount = match mode {
+            TransferMode::All { keep_alive } => {
+                let max = self
+                    .max_transferable_with_tip(from, &to, *keep_alive, tip)
+                    .await?;
+                if max == 0 {
+                    return Err(Error::Other("Nothing left to transfer after fees".into()));
+                }
+                max
+            }
+            _ => self.planck(&amount.into())?,
+        };
+        let dest = MultiAddress::from(to);
+        let client = self.client();
+        match mode {
+            TransferMode::AllowDeath | TransferMode::All { keep_alive: false } => {
+                let call = TransferCall { to: &dest, amount };
+                client.create_signed_reserved(call, &signer).await
+            }
+            TransferMode::KeepAlive | TransferMode::All { keep_alive: true } => {
+                let call = TransferKeepAliveCall { to: &dest, amount };
+                client.create_signed_reserved(call, &signer).await
+            }
+            TransferMode::Force { source } => {
+                let source = MultiAddress::from(source.clone());
+                let call = client.encode(ForceTransferCall {
+                    source: &source,
+                    dest: &dest,
+                    value: amount,
+                })?;
+                let sudo = SudoCall {
+                    _runtime: PhantomData,
+                    call: &call,
+                };
+                client.create_signed_reserved(sudo, &signer).await
+            }
+        }
+    }
+
+    /// Submits a transfer extrinsic and builds its receipt once the status in `wait` is
+    /// reached.
+    async fn watch_transfer(
+        &self,
+        signed: Signed,
+        wait: WaitFor,
+    ) -> Result<TransferReceipt, Error> {
+        let signer = match &signed.0.signature {
+            Some((MultiAddress::Id(signer), _, _)) => signer.clone(),
+            _ => return Err("Transfer extrinsic is not signed by an account id".into()),
+        };
+        let progress = self.watch_signed(signed).await?;
+        let inclusion = self.wait_for_inclusion(progress, &signer, wait).await?;
+        let event = inclusion.transfers()?.into_iter().next();
+        Ok(inclusion.receipt(event))
+    }
 
-            let hash = match client.transfer(&signer, &dest, self.amount).await {
-                Ok(h) => h,
-                Err(e) => return Err(e),
+    /// Follows the pool status of an extrinsic submitted by `signer` until `wait` is reached
+    /// and collects the events it emitted.
+    pub(crate) async fn wait_for_inclusion(
+        &self,
+        mut progress: TransactionProgress<IndracoreRuntime>,
+        signer: &AccountId32,
+        wait: WaitFor,
+    ) -> Result<Inclusion, Error> {
+        let client = self.client();
+        let extrinsic_hash = progress.extrinsic_hash();
+        let block_hash = loop {
+            let status = match progress.next().await {
+                Some(status) => status,
+                None => return Err("Transaction progress finished without a block".into()),
             };
-            Ok(hash)
+            match status {
+                TransactionStatus::InBlock(hash) if wait == WaitFor::InBlock => break hash,
+                TransactionStatus::Finalized(hash) => break hash,
+                status => match TransactionError::from_status(extrinsic_hash, &status) {
+                    Some(error) => return Err(error.into()),
+                    None => continue,
+                },
+            }
+        };
+
+        let header = client
+            .header(Some(block_hash))
+            .await?
+            .ok_or_else(|| Error::Other(format!("Failed to find block {:?}", block_hash)))?;
+        let extrinsic_index = client.extrinsic_index(block_hash, extrinsic_hash).await? as u32;
+        let mut events = Vec::new();
+        let mut error = None;
+        for (phase, raw) in client.events_at(block_hash).await?.events {
+            if phase != Phase::ApplyExtrinsic(extrinsic_index) {
+                continue;
+            }
+            match raw {
+                Raw::Event(event) => events.push(event),
+                Raw::Error(err) => error = Some(err),
+            }
+        }
+
+        let fee = fees_paid(&events, signer)?;
+        Ok(Inclusion {
+            extrinsic: extrinsic_hash,
+            block_hash,
+            block_number: header.number,
+            extrinsic_index,
+            events,
+            fee: self.token(fee),
+            error,
         })
     }
 }
 
+/// An extrinsic included in a block together with the events it emitted.
+pub(crate) struct Inclusion {
+    extrinsic: H256,
+    block_hash: H256,
+    block_number: u32,
+    extrinsic_index: u32,
+    events: Vec<RawEvent>,
+    fee: TokenAmount,
+    error: Option<RuntimeError>,
+}
+
+impl Inclusion {
+    /// Decodes the `Balances.Transfer` events in the order they were emitted.
+    pub(crate) fn transfers(&self) -> Result<Vec<TransferEvent<IndracoreRuntime>>, Error> {
+        let mut transfers = Vec::new();
+        for event in &self.events {
+            if event.module == "Balances" && event.variant == "Transfer" {
+                transfers.push(TransferEvent::decode(&mut &event.data[..])?);
+            }
+        }
+        Ok(transfers)
+    }
+
+    pub(crate) fn receipt(
+        &self,
+        event: Option<TransferEvent<IndracoreRuntime>>,
+    ) -> TransferReceipt {
+        TransferReceipt {
+            extrinsic: self.extrinsic,
+            block_hash: self.block_hash,
+            block_number: self.block_number,
+            extrinsic_index: self.extrinsic_index,
+            event,
+            fee: self.fee.clone(),
+            error: self.error.clone(),
+        }
+    }
+}
+
+/// Sums what an extrinsic paid in fees and tip from the deposits to the treasury and the
+/// block author among its events. The refund of unused weight is deposited back to `signer`
+/// and is not part of the fee.
+fn fees_paid(events: &[RawEvent], signer: &AccountId32) -> Result<u128, Error> {
+    let mut fee = 0u128;
+    for event in events {
+        let amount = match (event.module.as_str(), event.variant.as_str()) {
+            ("Balances", "Deposit") => {
+                let deposit = DepositEvent::<IndracoreRuntime>::decode(&mut &event.data[..])?;
+                if &deposit.who == signer {
+                    continue;
+                }
+                deposit.amount
+            }
+            ("Treasury", "Deposit") => u128::decode(&mut &event.data[..])?,
+            _ => continue,
+        };
+        fee = fee.saturating_add(amount);
+    }
+    Ok(fee)
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
+    use codec::Encode;
+    use indracore_subxt::sp_runtime::transaction_validity::{
+        InvalidTransaction, TransactionValidityError,
+    };
 
     #[test]
-    fn test_transfer() {
-        let to = "5FTussKPbMZReFdvL7tu97XzQe7uGqHtcH1PwKN9xCBN2WJ7";
+    fn test_fees_paid_excludes_refund() {
+        let signer = AccountId32::from([1u8; 32]);
+        let author = AccountId32::from([2u8; 32]);
+        let deposit = |who: &AccountId32, amount: u128| RawEvent {
+            module: "Balances".into(),
+            variant: "Deposit".into(),
+            data: (who, amount).encode(),
+        };
+        let events = vec![
+            deposit(&signer, 300),
+            RawEvent {
+                module: "Treasury".into(),
+                variant: "Deposit".into(),
+                data: 800u128.encode(),
+            },
+            deposit(&author, 200),
+        ];
+        assert_eq!(fees_paid(&events, &signer).unwrap(), 1_000);
+    }
+
+    #[async_std::test]
+    async fn test_transfer() {
+        let to = "ABsHC7rqRhdEr3uCHpvfAdZukFr2KUYsiJ1iHzoy8oNVAR7";
         let from = "0x9abdf3e8edda03c1708bcd5bc3353e91efd503fd9105ff0ee68a7cbc66b740d8";
         let url = "ws://127.0.0.1:9944";
-        let data = Transfer::new(url, from, to, 10_0000);
-        assert!(data.transfer().is_err());
+        let data = Transfer::new(url, from, to, 10_0000u128);
+        assert!(data.transfer().await.is_err());
+    }
+
+    #[async_std::test]
+    async fn test_transfer_and_watch() {
+        let to = "ABsHC7rqRhdEr3uCHpvfAdZukFr2KUYsiJ1iHzoy8oNVAR7";
+        let url = "ws://127.0.0.1:9944";
+        let receipt = Transfer::new(url, "//Alice", to, 10_000_000_000u128)
+            .transfer_and_watch(WaitFor::InBlock)
+            .await
+            .unwrap();
+        assert!(receipt.is_success());
+        let event = receipt.event.unwrap();
+        assert_eq!(event.to, IndraKeyring::accountid_from_str(to).unwrap());
+        assert_eq!(event.amount, 10_000_000_000);
+        assert!(receipt.fee.planck() > 0);
+    }
+
+    #[async_std::test]
+    async fn test_transfer_with_tip() {
+        let to = "ABsHC7rqRhdEr3uCHpvfAdZukFr2KUYsiJ1iHzoy8oNVAR7";
+        let url = "ws://127.0.0.1:9944";
+        let transfer = Transfer::new(url, "//Bob", to, 10_000_000_000u128);
+        // a tip well above the fee only shows up in the paid fee if it was deposited
+        let tip = transfer.estimate_fee().await.unwrap().planck() * 10;
+        let receipt = transfer
+            .set_tip(tip)
+            .transfer_and_watch(WaitFor::InBlock)
+            .await
+            .unwrap();
+        assert!(receipt.is_success());
+        assert!(receipt.fee.planck() > tip);
+    }
+
+    #[async_std::test]
+    async fn test_estimate_fee() {
+        let to = "ABsHC7rqRhdEr3uCHpvfAdZukFr2KUYsiJ1iHzoy8oNVAR7";
+        let url = "ws://127.0.0.1:9944";
+        let transfer = Transfer::new(url, "//Alice", to, 10_000_000_000u128);
+        let fee = transfer.estimate_fee().await.unwrap();
+        assert!(fee.planck() > 0);
+        let tipped = transfer.set_tip(1_000u128).estimate_fee().await.unwrap();
+        assert!(tipped.planck() >= fee.planck() + 1_000);
+    }
+
+    #[async_std::test]
+    async fn test_concurrent_transfers() {
+        let indra = Indra::new("ws://127.0.0.1:9944").await.unwrap();
+        let to = "ABsHC7rqRhdEr3uCHpvfAdZukFr2KUYsiJ1iHzoy8oNVAR7";
+        let (first, second) = futures::join!(
+            indra.transfer_and_watch("//Charlie", to, 10_000_000_000u128, WaitFor::InBlock),
+            indra.transfer_and_watch("//Charlie", to, 10_000_000_000u128, WaitFor::InBlock),
+        );
+        assert!(first.unwrap().is_success());
+        assert!(second.unwrap().is_success());
+    }
+
+    #[async_std::test]
+    async fn test_rejected_transfer_releases_nonce() {
+        let indra = Indra::new("ws://127.0.0.1:9944").await.unwrap();
+        let to = "ABsHC7rqRhdEr3uCHpvfAdZukFr2KUYsiJ1iHzoy8oNVAR7";
+        // an account without funds cannot pay the fee
+        let from = "//Unfunded";
+        assert!(indra.transfer(from, to, 1u128).await.is_err());
+        let account = IndraKeyring::from_str(from).unwrap().accountid();
+        assert!(indra.client().nonces().in_flight(&account).is_empty());
+    }
+
+    #[async_std::test]
+    async fn test_stale_nonce() {
+        let indra = Indra::new("ws://127.0.0.1:9944").await.unwrap();
+        let to = "ABsHC7rqRhdEr3uCHpvfAdZukFr2KUYsiJ1iHzoy8oNVAR7";
+        indra
+            .transfer_and_watch("//Dave", to, 10_000_000_000u128, WaitFor::InBlock)
+            .await
+            .unwrap();
+        let options = TransferSigner {
+            tip: None,
+            nonce: Some(0),
+        };
+        let extrinsic = indra
+            .create_transfer(
+                "//Dave",
+                to,
+                10_000_000_000u128,
+                &TransferMode::KeepAlive,
+                options,
+            )
+            .await
+            .unwrap();
+        match indra.submit_signed(extrinsic).await {
+            Err(Error::Invalid(TransactionValidityError::Invalid(InvalidTransaction::Stale))) => {}
+            result => panic!("expected a stale transaction, got {:?}", result),
+        }
+    }
+
+    #[async_std::test]
+    async fn test_max_transferable() {
+        let indra = Indra::new("ws://127.0.0.1:9944").await.unwrap();
+        let to = "ABsHC7rqRhdEr3uCHpvfAdZukFr2KUYsiJ1iHzoy8oNVAR7";
+        let alice = IndraKeyring::from_str("//Alice").unwrap();
+        let free = indra.get_free_balance(alice.accountid()).await.unwrap();
+        let max = indra.max_transferable("//Alice", to, true).await.unwrap();
+        assert!(max < free);
     }
 }
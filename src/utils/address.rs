@@ -0,0 +1,124 @@
+use crate::utils::keyring::IntoAccountId;
+use indracore_subxt::{
+    sp_core::crypto::{AccountId32, Ss58AddressFormat, Ss58Codec},
+    sp_runtime::MultiAddress,
+    Error,
+};
+use std::fmt;
+
+/// An account address bound to the SS58 network prefix of a chain.
+///
+/// Parsing is strict: an address encoded for another network is rejected instead of being
+/// silently reinterpreted. `Display` renders the address with the chain's prefix.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IndraAddress {
+    account_id: AccountId32,
+    ss58_format: u8,
+}
+
+impl IndraAddress {
+    pub fn new(account_id: AccountId32, ss58_format: u8) -> IndraAddress {
+        Self {
+            account_id,
+            ss58_format,
+        }
+    }
+
+    /// Parses an SS58 address, requiring it to be encoded with `ss58_format`.
+    pub fn parse(address: impl AsRef<str>, ss58_format: u8) -> Result<IndraAddress, Error> {
+        let address = address.as_ref();
+        let (account_id, version) = AccountId32::from_ss58check_with_version(address)
+            .map_err(|e| Error::Other(format!("Invalid SS58 address {}: {:?}", address, e)))?;
+        let version = u8::from(version);
+        if version != ss58_format {
+            return Err(Error::Other(format!(
+                "Address {} belongs to network prefix {}, expected prefix {}",
+                address, version, ss58_format
+            )));
+        }
+        Ok(Self::new(account_id, ss58_format))
+    }
+
+    pub fn account_id(&self) -> &AccountId32 {
+        &self.account_id
+    }
+
+    pub fn ss58_format(&self) -> u8 {
+        self.ss58_format
+    }
+
+    pub fn multi_address(&self) -> MultiAddress<AccountId32, ()> {
+        MultiAddress::from(self.account_id.clone())
+    }
+}
+
+impl fmt::Display for IndraAddress {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let format = Ss58AddressFormat::Custom(self.ss58_format);
+        write!(f, "{}", self.account_id.to_ss58check_with_version(format))
+    }
+}
+
+impl From<IndraAddress> for AccountId32 {
+    fn from(address: IndraAddress) -> Self {
+        address.account_id
+    }
+}
+
+impl From<IndraAddress> for MultiAddress<AccountId32, ()> {
+    fn from(address: IndraAddress) -> Self {
+        MultiAddress::from(address.account_id)
+    }
+}
+
+impl IntoAccountId for IndraAddress {
+    fn into_account_id(self, ss58_format: u8) -> Result<AccountId32, Error> {
+        (&self).into_account_id(ss58_format)
+    }
+}
+
+impl IntoAccountId for &IndraAddress {
+    fn into_account_id(self, ss58_format: u8) -> Result<AccountId32, Error> {
+        if self.ss58_format != ss58_format {
+            return Err(Error::Other(format!(
+                "Address {} belongs to network prefix {}, expected prefix {}",
+                self, self.ss58_format, ss58_format
+            )));
+        }
+        Ok(self.account_id.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ALICE_GENERIC: &str = "5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY";
+    const ALICE_KUSAMA: &str = "HNZata7iMYWmk5RvZRTiAsSDhV8366zq2YGb3tLH5Upf74F";
+
+    #[test]
+    fn test_parse_and_render() {
+        let generic = IndraAddress::parse(ALICE_GENERIC, 42).unwrap();
+        let kusama = IndraAddress::parse(ALICE_KUSAMA, 2).unwrap();
+        assert_eq!(generic.account_id(), kusama.account_id());
+        assert_eq!(generic.to_string(), ALICE_GENERIC);
+        assert_eq!(
+            IndraAddress::new(generic.into(), 2).to_string(),
+            ALICE_KUSAMA
+        );
+    }
+
+    #[test]
+    fn test_reject_other_network() {
+        assert!(IndraAddress::parse(ALICE_KUSAMA, 42).is_err());
+        assert!(IndraAddress::parse(ALICE_GENERIC, 2).is_err());
+    }
+
+    #[test]
+    fn test_reject_bad_checksum() {
+        let mut address = ALICE_GENERIC.to_string();
+        address.pop();
+        address.push('Z');
+        assert!(IndraAddress::parse(address, 42).is_err());
+    }
+}
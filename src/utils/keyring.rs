@@ -1,34 +1,187 @@
+use crate::utils::address::IndraAddress;
+use bip39::{Language, Mnemonic, MnemonicType};
 use indracore_subxt::{
-    sp_core::{crypto, sr25519, Pair as TraitPair},
-    sp_runtime, Error, IndracoreRuntime, PairSigner,
+    extrinsic::SignedPayload,
+    sp_core::{crypto, ecdsa, ed25519, sr25519, Pair as TraitPair},
+    sp_runtime::{self, traits::IdentifyAccount, MultiSigner},
+    Error, IndracoreRuntime, PairSigner, Signer, UncheckedExtrinsic,
 };
-use std::str::FromStr;
+use std::{future::Future, pin::Pin, str::FromStr};
 
-type KusamaPairSigner = PairSigner<IndracoreRuntime, sr25519::Pair>;
 type MultiAddressId = sp_runtime::MultiAddress<crypto::AccountId32, ()>;
 
+/// Signature schemes accepted by the runtime's `MultiSignature`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CryptoScheme {
+    Sr25519,
+    /// Supports hard derivation only.
+    Ed25519,
+    /// Supports hard derivation only.
+    Ecdsa,
+}
+
+impl Default for CryptoScheme {
+    fn default() -> Self {
+        CryptoScheme::Sr25519
+    }
+}
+
+/// A [`PairSigner`] of any of the supported schemes.
+#[derive(Clone)]
+pub enum IndraSigner {
+    Sr25519(PairSigner<IndracoreRuntime, sr25519::Pair>),
+    Ed25519(PairSigner<IndracoreRuntime, ed25519::Pair>),
+    Ecdsa(PairSigner<IndracoreRuntime, ecdsa::Pair>),
+}
+
+impl IndraSigner {
+    pub fn set_nonce(&mut self, nonce: u32) {
+        match self {
+            IndraSigner::Sr25519(signer) => signer.set_nonce(nonce),
+            IndraSigner::Ed25519(signer) => signer.set_nonce(nonce),
+            IndraSigner::Ecdsa(signer) => signer.set_nonce(nonce),
+        }
+    }
+
+    pub fn increment_nonce(&mut self) {
+        match self {
+            IndraSigner::Sr25519(signer) => signer.increment_nonce(),
+            IndraSigner::Ed25519(signer) => signer.increment_nonce(),
+            IndraSigner::Ecdsa(signer) => signer.increment_nonce(),
+        }
+    }
+
+    /// Sets the tip paid to the block author with every transaction signed.
+    pub fn set_tip(&mut self, tip: u128) {
+        match self {
+            IndraSigner::Sr25519(signer) => signer.set_tip(tip),
+            IndraSigner::Ed25519(signer) => signer.set_tip(tip),
+            IndraSigner::Ecdsa(signer) => signer.set_tip(tip),
+        }
+    }
+}
+
+impl Signer<IndracoreRuntime> for IndraSigner {
+    fn account_id(&self) -> &crypto::AccountId32 {
+        match self {
+            IndraSigner::Sr25519(signer) => signer.account_id(),
+            IndraSigner::Ed25519(signer) => signer.account_id(),
+            IndraSigner::Ecdsa(signer) => signer.account_id(),
+        }
+    }
+
+    fn nonce(&self) -> Option<u32> {
+        match self {
+            IndraSigner::Sr25519(signer) => signer.nonce(),
+            IndraSigner::Ed25519(signer) => signer.nonce(),
+            IndraSigner::Ecdsa(signer) => signer.nonce(),
+        }
+    }
+
+    fn tip(&self) -> Option<u128> {
+        match self {
+            IndraSigner::Sr25519(signer) => signer.tip(),
+            IndraSigner::Ed25519(signer) => signer.tip(),
+            IndraSigner::Ecdsa(signer) => signer.tip(),
+        }
+    }
+
+    fn sign(
+        &self,
+        extrinsic: SignedPayload<IndracoreRuntime>,
+    ) -> Pin<Box<dyn Future<Output = Result<UncheckedExtrinsic<IndracoreRuntime>, String>> + Send>>
+    {
+        match self {
+            IndraSigner::Sr25519(signer) => signer.sign(extrinsic),
+            IndraSigner::Ed25519(signer) => signer.sign(extrinsic),
+            IndraSigner::Ecdsa(signer) => signer.sign(extrinsic),
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct IndraKeyring {
-    pairsigner: KusamaPairSigner,
+    pairsigner: IndraSigner,
+    public: MultiSigner,
     accountid: crypto::AccountId32,
 }
 
 impl IndraKeyring {
-    pub fn from_str(mnemonic: &'static str) -> Result<IndraKeyring, Error> {
-        match sr25519::Pair::from_string(&mnemonic, None) {
-            Ok(pair) => {
-                let pairsigner = PairSigner::<IndracoreRuntime, sr25519::Pair>::new(pair.clone());
-                let accountid = crypto::AccountId32::from(pair.clone().public());
-                Ok(Self {
-                    pairsigner,
-                    accountid,
-                })
+    /// Imports an sr25519 key from a mnemonic, seed or SURI such as `//Alice`.
+    pub fn from_str(mnemonic: impl AsRef<str>) -> Result<IndraKeyring, Error> {
+        Self::from_suri(mnemonic, CryptoScheme::Sr25519, None)
+    }
+
+    /// Imports a key of `scheme` from a mnemonic or seed with an optional derivation path,
+    /// e.g. `"<mnemonic>//hard/soft"` or `"//Alice//stash"`.
+    ///
+    /// `password` takes precedence over a `///password` part of the SURI.
+    pub fn from_suri(
+        suri: impl AsRef<str>,
+        scheme: CryptoScheme,
+        password: Option<&str>,
+    ) -> Result<IndraKeyring, Error> {
+        let suri = suri.as_ref();
+        match scheme {
+            CryptoScheme::Sr25519 => {
+                let pair = sr25519::Pair::from_string(suri, password)
+                    .map_err(|e| Error::Other(format!("{:?}", e)))?;
+                Ok(Self::new(
+                    IndraSigner::Sr25519(PairSigner::new(pair.clone())),
+                    pair.public().into(),
+                ))
+            }
+            CryptoScheme::Ed25519 => {
+                let pair = ed25519::Pair::from_string(suri, password)
+                    .map_err(|e| Error::Other(format!("{:?}", e)))?;
+                Ok(Self::new(
+                    IndraSigner::Ed25519(PairSigner::new(pair.clone())),
+                    pair.public().into(),
+                ))
+            }
+            CryptoScheme::Ecdsa => {
+                let pair = ecdsa::Pair::from_string(suri, password)
+                    .map_err(|e| Error::Other(format!("{:?}", e)))?;
+                Ok(Self::new(
+                    IndraSigner::Ecdsa(PairSigner::new(pair.clone())),
+                    pair.public().into(),
+                ))
             }
-            Err(e) => Err(Error::Other(format!("{:?}", e))),
         }
     }
 
-    pub fn pairsingner(&self) -> KusamaPairSigner {
+    /// Generates a new English BIP39 mnemonic of 12, 15 or 24 words.
+    pub fn generate_mnemonic(words: usize) -> Result<String, Error> {
+        let mnemonic_type = match words {
+            12 => MnemonicType::Words12,
+            15 => MnemonicType::Words15,
+            24 => MnemonicType::Words24,
+            _ => {
+                return Err(Error::Other(format!(
+                    "Unsupported mnemonic length {}, expected 12, 15 or 24 words",
+                    words
+                )))
+            }
+        };
+        Ok(Mnemonic::new(mnemonic_type, Language::English).into_phrase())
+    }
+
+    /// Creates a key of `scheme` from a new mnemonic, returning the key and its phrase.
+    pub fn generate(words: usize, scheme: CryptoScheme) -> Result<(IndraKeyring, String), Error> {
+        let phrase = Self::generate_mnemonic(words)?;
+        let keyring = Self::from_suri(&phrase, scheme, None)?;
+        Ok((keyring, phrase))
+    }
+
+    pub(crate) fn new(pairsigner: IndraSigner, public: MultiSigner) -> Self {
+        Self {
+            pairsigner,
+            accountid: public.clone().into_account(),
+            public,
+        }
+    }
+
+    pub fn pairsingner(&self) -> IndraSigner {
         self.pairsigner.clone()
     }
 
@@ -36,21 +189,80 @@ impl IndraKeyring {
         self.accountid.clone()
     }
 
-    pub fn accountid_from_str(accountid: &'static str) -> Result<crypto::AccountId32, Error> {
-        match sp_runtime::AccountId32::from_str(accountid) {
+    pub fn scheme(&self) -> CryptoScheme {
+        match self.public {
+            MultiSigner::Sr25519(_) => CryptoScheme::Sr25519,
+            MultiSigner::Ed25519(_) => CryptoScheme::Ed25519,
+            MultiSigner::Ecdsa(_) => CryptoScheme::Ecdsa,
+        }
+    }
+
+    /// Returns the public key. For ecdsa the account id is the blake2 hash of the key rather
+    /// than the key itself.
+    pub fn public(&self) -> &MultiSigner {
+        &self.public
+    }
+
+    /// Returns the address of the key in the `ss58_format` of a network.
+    pub fn address(&self, ss58_format: u8) -> IndraAddress {
+        IndraAddress::new(self.accountid(), ss58_format)
+    }
+
+    /// Decodes an SS58 address of any known network prefix, see [`IndraAddress::parse`] to
+    /// require the prefix of a chain.
+    pub fn accountid_from_str(accountid: impl AsRef<str>) -> Result<crypto::AccountId32, Error> {
+        match sp_runtime::AccountId32::from_str(accountid.as_ref()) {
             Ok(id) => Ok(id),
             Err(e) => return Err(Error::Other(e.into())),
         }
     }
 
-    pub fn multi_address_from_str(accountid: &'static str) -> Result<MultiAddressId, Error> {
-        match sp_runtime::AccountId32::from_str(accountid) {
+    pub fn multi_address_from_str(accountid: impl AsRef<str>) -> Result<MultiAddressId, Error> {
+        match sp_runtime::AccountId32::from_str(accountid.as_ref()) {
             Ok(id) => Ok(sp_runtime::MultiAddress::from(id)),
             Err(e) => return Err(Error::Other(e.into())),
         }
     }
 }
 
+/// Values that name an account, either already decoded or as an SS58 string.
+///
+/// Strings must be encoded with the chain's `ss58_format`, an address of another network is
+/// rejected rather than reinterpreted.
+pub trait IntoAccountId {
+    fn into_account_id(self, ss58_format: u8) -> Result<crypto::AccountId32, Error>;
+}
+
+impl IntoAccountId for crypto::AccountId32 {
+    fn into_account_id(self, _ss58_format: u8) -> Result<crypto::AccountId32, Error> {
+        Ok(self)
+    }
+}
+
+impl IntoAccountId for &crypto::AccountId32 {
+    fn into_account_id(self, _ss58_format: u8) -> Result<crypto::AccountId32, Error> {
+        Ok(self.clone())
+    }
+}
+
+impl IntoAccountId for &str {
+    fn into_account_id(self, ss58_format: u8) -> Result<crypto::AccountId32, Error> {
+        IndraAddress::parse(self, ss58_format).map(Into::into)
+    }
+}
+
+impl IntoAccountId for String {
+    fn into_account_id(self, ss58_format: u8) -> Result<crypto::AccountId32, Error> {
+        self.as_str().into_account_id(ss58_format)
+    }
+}
+
+impl IntoAccountId for &String {
+    fn into_account_id(self, ss58_format: u8) -> Result<crypto::AccountId32, Error> {
+        self.as_str().into_account_id(ss58_format)
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -67,6 +279,56 @@ mod test {
         assert_eq!(mnemonic_pair.accountid, seed_pair.accountid)
     }
 
+    #[test]
+    fn test_schemes_and_derivation() {
+        let alice = IndraKeyring::from_str("//Alice").unwrap();
+        assert_eq!(
+            alice.accountid(),
+            IndraKeyring::accountid_from_str("5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY")
+                .unwrap()
+        );
+        let alice_ed = IndraKeyring::from_suri("//Alice", CryptoScheme::Ed25519, None).unwrap();
+        assert_eq!(alice_ed.scheme(), CryptoScheme::Ed25519);
+        assert_eq!(
+            alice_ed.accountid(),
+            IndraKeyring::accountid_from_str("5FA9nQDVg267DEd8m1ZypXLBnvN7SFxYwV7ndqSYGiN9TTpu")
+                .unwrap()
+        );
+        let alice_ecdsa = IndraKeyring::from_suri("//Alice", CryptoScheme::Ecdsa, None).unwrap();
+        assert_ne!(alice_ecdsa.accountid(), alice.accountid());
+
+        let stash = IndraKeyring::from_str("//Alice//stash").unwrap();
+        assert_ne!(stash.accountid(), alice.accountid());
+        let soft = IndraKeyring::from_str("//Alice/soft").unwrap();
+        assert_ne!(soft.accountid(), alice.accountid());
+        assert!(IndraKeyring::from_suri("//Alice/soft", CryptoScheme::Ed25519, None).is_err());
+    }
+
+    #[test]
+    fn test_password() {
+        let mnemonic =
+            "plug math bacon find roast scrap shrug exchange announce october exclude plate";
+        let plain = IndraKeyring::from_str(mnemonic).unwrap();
+        let protected =
+            IndraKeyring::from_suri(mnemonic, CryptoScheme::Sr25519, Some("secret")).unwrap();
+        let in_suri = IndraKeyring::from_str(format!("{}///secret", mnemonic)).unwrap();
+        assert_ne!(plain.accountid(), protected.accountid());
+        assert_eq!(protected.accountid(), in_suri.accountid());
+    }
+
+    #[test]
+    fn test_generate() {
+        for words in &[12, 15, 24] {
+            let (keyring, phrase) = IndraKeyring::generate(*words, CryptoScheme::Sr25519).unwrap();
+            assert_eq!(phrase.split_whitespace().count(), *words);
+            assert_eq!(
+                IndraKeyring::from_str(&phrase).unwrap().accountid(),
+                keyring.accountid()
+            );
+        }
+        assert!(IndraKeyring::generate_mnemonic(13).is_err());
+    }
+
     #[test]
     fn test_get_accountid_from_string() {
         let alice = "BauKu2iL4fncgfy22YSLGc1aDLpyuUUe5z8yNF2pDtLNr4E";
@@ -84,4 +346,20 @@ mod test {
         let bob_id = IndraKeyring::multi_address_from_str(bob);
         assert_ne!(alice_id.unwrap(), bob_id.unwrap());
     }
+
+    #[test]
+    fn test_into_account_id() {
+        let alice = String::from("BauKu2iL4fncgfy22YSLGc1aDLpyuUUe5z8yNF2pDtLNr4E");
+        let alice_id = IndraKeyring::accountid_from_str(&alice).unwrap();
+        assert_eq!(alice.into_account_id(1).unwrap(), alice_id);
+        assert_eq!((&alice_id).into_account_id(1).unwrap(), alice_id);
+        assert!("not an address".into_account_id(1).is_err());
+        // the same account encoded with the generic substrate prefix
+        assert!("5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY"
+            .into_account_id(1)
+            .is_err());
+        let address = IndraAddress::new(alice_id.clone(), 42);
+        assert!((&address).into_account_id(1).is_err());
+        assert_eq!(address.into_account_id(42).unwrap(), alice_id);
+    }
 }
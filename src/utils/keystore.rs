@@ -0,0 +1,315 @@
+//! Encrypted JSON keystore files in the format of the Polkadot-JS keyring.
+//!
+//! The secret is stored as PKCS8 and sealed with xsalsa20-poly1305 under a key derived from
+//! the password with scrypt, so files can be moved between this crate and browser wallets.
+
+use crate::utils::keyring::{CryptoScheme, IndraKeyring, IndraSigner};
+use indracore_subxt::{
+    sp_core::{ecdsa, ed25519, sr25519, Pair},
+    sp_runtime::MultiSigner,
+    Error, PairSigner,
+};
+use rand::{rngs::OsRng, RngCore};
+use schnorrkel::SecretKey;
+use scrypt::{scrypt, ScryptParams};
+use serde::{Deserialize, Serialize};
+use std::time::{SystemTime, UNIX_EPOCH};
+use xsalsa20poly1305::{
+    aead::{generic_array::GenericArray, Aead, NewAead},
+    XSalsa20Poly1305,
+};
+
+const PKCS8_HEADER: [u8; 16] = [48, 83, 2, 1, 1, 48, 5, 6, 3, 43, 101, 112, 4, 34, 4, 32];
+const PKCS8_DIVIDER: [u8; 5] = [161, 35, 3, 33, 0];
+const SECRET_LENGTH: usize = 64;
+const SEED_LENGTH: usize = 32;
+
+const SCRYPT_LOG_N: u8 = 15;
+const SCRYPT_P: u32 = 1;
+const SCRYPT_R: u32 = 8;
+const SALT_LENGTH: usize = 32;
+const NONCE_LENGTH: usize = 24;
+
+#[derive(Serialize, Deserialize)]
+struct Keystore {
+    address: String,
+    encoded: String,
+    encoding: Encoding,
+    #[serde(default)]
+    meta: serde_json::Value,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Encoding {
+    content: Vec<String>,
+    #[serde(rename = "type")]
+    ty: Vec<String>,
+    version: String,
+}
+
+impl IndraKeyring {
+    /// Exports the key as an encrypted JSON keystore that Polkadot-JS wallets can import.
+    ///
+    /// The keystore holds the derived secret, so a key imported from a SURI with a derivation
+    /// path is exported as that derived key.
+    pub fn to_json(
+        &self,
+        password: impl AsRef<str>,
+        ss58_format: u8,
+        name: impl Into<String>,
+    ) -> Result<String, Error> {
+        let (scheme, secret, public) = match self.pairsingner() {
+            IndraSigner::Sr25519(signer) => {
+                let secret = SecretKey::from_bytes(&signer.signer().to_raw_vec())
+                    .map_err(|e| Error::Other(format!("{:?}", e)))?;
+                (
+                    "sr25519",
+                    secret.to_ed25519_bytes().to_vec(),
+                    signer.signer().public().as_ref().to_vec(),
+                )
+            }
+            IndraSigner::Ed25519(signer) => {
+                let public = signer.signer().public().as_ref().to_vec();
+                let mut secret = signer.signer().seed().to_vec();
+                secret.extend_from_slice(&public);
+                ("ed25519", secret, public)
+            }
+            IndraSigner::Ecdsa(signer) => (
+                "ecdsa",
+                signer.signer().seed().to_vec(),
+                signer.signer().public().as_ref().to_vec(),
+            ),
+        };
+
+        let mut pkcs8 = PKCS8_HEADER.to_vec();
+        pkcs8.extend_from_slice(&secret);
+        pkcs8.extend_from_slice(&PKCS8_DIVIDER);
+        pkcs8.extend_from_slice(&public);
+
+        let when_created = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_millis() as u64)
+            .unwrap_or_default();
+        let keystore = Keystore {
+            address: self.address(ss58_format).to_string(),
+            encoded: base64::encode(encrypt(&pkcs8, password.as_ref().as_bytes())?),
+            encoding: Encoding {
+                content: vec!["pkcs8".into(), scheme.into()],
+                ty: vec!["scrypt".into(), "xsalsa20-poly1305".into()],
+                version: "3".into(),
+            },
+            meta: serde_json::json!({
+                "name": name.into(),
+                "whenCreated": when_created,
+            }),
+        };
+        Ok(serde_json::to_string(&keystore)?)
+    }
+
+    /// Imports a key from an encrypted JSON keystore of a Polkadot-JS wallet.
+    pub fn from_json(
+        json: impl AsRef<str>,
+        password: impl AsRef<str>,
+    ) -> Result<IndraKeyring, Error> {
+        let keystore: Keystore = serde_json::from_str(json.as_ref())?;
+        let encoding = &keystore.encoding;
+        if encoding.version != "3"
+            || encoding.ty != ["scrypt", "xsalsa20-poly1305"]
+            || encoding.content.first().map(String::as_str) != Some("pkcs8")
+        {
+            return Err(Error::Other(format!(
+                "Unsupported keystore encoding {:?} version {}",
+                encoding.ty, encoding.version
+            )));
+        }
+        let scheme = match encoding.content.get(1).map(String::as_str) {
+            Some("sr25519") => CryptoScheme::Sr25519,
+            Some("ed25519") => CryptoScheme::Ed25519,
+            Some("ecdsa") => CryptoScheme::Ecdsa,
+            other => {
+                return Err(Error::Other(format!(
+                    "Unsupported key type {:?}",
+                    other.unwrap_or_default()
+                )))
+            }
+        };
+        let encoded = base64::decode(&keystore.encoded)
+            .map_err(|e| Error::Other(format!("Invalid keystore encoding: {}", e)))?;
+        let pkcs8 = decrypt(&encoded, password.as_ref().as_bytes())?;
+        let (secret, public) = decode_pkcs8(&pkcs8)?;
+
+        let invalid = |e| Error::Other(format!("Invalid secret key: {:?}", e));
+        let keyring = match scheme {
+            CryptoScheme::Sr25519 => {
+                let secret = SecretKey::from_ed25519_bytes(secret)
+                    .map_err(|e| Error::Other(format!("Invalid secret key: {:?}", e)))?;
+                let pair = sr25519::Pair::from_seed_slice(&secret.to_bytes()).map_err(invalid)?;
+                IndraKeyring::new(
+                    IndraSigner::Sr25519(PairSigner::new(pair.clone())),
+                    pair.public().into(),
+                )
+            }
+            CryptoScheme::Ed25519 => {
+                let pair =
+                    ed25519::Pair::from_seed_slice(&secret[..SEED_LENGTH]).map_err(invalid)?;
+                IndraKeyring::new(
+                    IndraSigner::Ed25519(PairSigner::new(pair.clone())),
+                    pair.public().into(),
+                )
+            }
+            CryptoScheme::Ecdsa => {
+                let pair = ecdsa::Pair::from_seed_slice(secret).map_err(invalid)?;
+                IndraKeyring::new(
+                    IndraSigner::Ecdsa(PairSigner::new(pair.clone())),
+                    pair.public().into(),
+                )
+            }
+        };
+        let derived: &[u8] = match keyring.public() {
+            MultiSigner::Sr25519(public) => public.as_ref(),
+            MultiSigner::Ed25519(public) => public.as_ref(),
+            MultiSigner::Ecdsa(public) => public.as_ref(),
+        };
+        if derived != public {
+            return Err(Error::Other(
+                "Keystore public key does not match its secret".into(),
+            ));
+        }
+        Ok(keyring)
+    }
+}
+
+/// Splits a PKCS8 document into the secret and public key.
+fn decode_pkcs8(pkcs8: &[u8]) -> Result<(&[u8], &[u8]), Error> {
+    let invalid = || Error::Other("Invalid PKCS8 key".into());
+    if !pkcs8.starts_with(&PKCS8_HEADER) {
+        return Err(invalid());
+    }
+    let body = &pkcs8[PKCS8_HEADER.len()..];
+    // sr25519 and ed25519 secrets are 64 bytes, ecdsa secrets are 32 byte seeds
+    for length in &[SECRET_LENGTH, SEED_LENGTH] {
+        if body.len() > *length && body[*length..].starts_with(&PKCS8_DIVIDER) {
+            let public = &body[length + PKCS8_DIVIDER.len()..];
+            return Ok((&body[..*length], public));
+        }
+    }
+    Err(invalid())
+}
+
+fn encrypt(plain: &[u8], password: &[u8]) -> Result<Vec<u8>, Error> {
+    let mut salt = [0u8; SALT_LENGTH];
+    OsRng.fill_bytes(&mut salt);
+    let mut nonce = [0u8; NONCE_LENGTH];
+    OsRng.fill_bytes(&mut nonce);
+
+    let key = derive_key(password, &salt, SCRYPT_LOG_N, SCRYPT_R, SCRYPT_P)?;
+    let cipher = XSalsa20Poly1305::new(GenericArray::from_slice(&key));
+    let sealed = cipher
+        .encrypt(GenericArray::from_slice(&nonce), plain)
+        .map_err(|_| Error::Other("Failed to encrypt key".into()))?;
+
+    let mut encoded = salt.to_vec();
+    encoded.extend_from_slice(&(1u32 << SCRYPT_LOG_N).to_le_bytes());
+    encoded.extend_from_slice(&SCRYPT_P.to_le_bytes());
+    encoded.extend_from_slice(&SCRYPT_R.to_le_bytes());
+    encoded.extend_from_slice(&nonce);
+    encoded.extend(sealed);
+    Ok(encoded)
+}
+
+fn decrypt(encoded: &[u8], password: &[u8]) -> Result<Vec<u8>, Error> {
+    let params_end = SALT_LENGTH + 12;
+    if encoded.len() < params_end + NONCE_LENGTH {
+        return Err(Error::Other("Keystore is too short".into()));
+    }
+    let salt = &encoded[..SALT_LENGTH];
+    let param = |index: usize| {
+        let start = SALT_LENGTH + index * 4;
+        let mut bytes = [0u8; 4];
+        bytes.copy_from_slice(&encoded[start..start + 4]);
+        u32::from_le_bytes(bytes)
+    };
+    let (n, p, r) = (param(0), param(1), param(2));
+    if !n.is_power_of_two() {
+        return Err(Error::Other(format!("Invalid scrypt parameter N {}", n)));
+    }
+    // the parameters come from the file, larger ones would let it demand any memory and time
+    if n > 1 << SCRYPT_LOG_N || p > SCRYPT_P || r > SCRYPT_R {
+        return Err(Error::Other(format!(
+            "Unsupported scrypt parameters N {}, p {}, r {}",
+            n, p, r
+        )));
+    }
+    let nonce = &encoded[params_end..params_end + NONCE_LENGTH];
+    let sealed = &encoded[params_end + NONCE_LENGTH..];
+
+    let key = derive_key(password, salt, n.trailing_zeros() as u8, r, p)?;
+    let cipher = XSalsa20Poly1305::new(GenericArray::from_slice(&key));
+    cipher
+        .decrypt(GenericArray::from_slice(nonce), sealed)
+        .map_err(|_| Error::Other("Invalid password or corrupted keystore".into()))
+}
+
+/// Returns the secretbox key, the first half of the 64 byte scrypt output.
+fn derive_key(password: &[u8], salt: &[u8], log_n: u8, r: u32, p: u32) -> Result<[u8; 32], Error> {
+    let params = ScryptParams::new(log_n, r, p)
+        .map_err(|e| Error::Other(format!("Invalid scrypt parameters: {}", e)))?;
+    let mut output = [0u8; 64];
+    scrypt(password, salt, &params, &mut output)
+        .map_err(|e| Error::Other(format!("Failed to derive key: {}", e)))?;
+    let mut key = [0u8; 32];
+    key.copy_from_slice(&output[..32]);
+    Ok(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keystore_roundtrip() {
+        for scheme in &[
+            CryptoScheme::Sr25519,
+            CryptoScheme::Ed25519,
+            CryptoScheme::Ecdsa,
+        ] {
+            let keyring = IndraKeyring::from_suri("//Alice//stash", *scheme, None).unwrap();
+            let json = keyring.to_json("secret", 42, "stash").unwrap();
+            let imported = IndraKeyring::from_json(&json, "secret").unwrap();
+            assert_eq!(imported.accountid(), keyring.accountid());
+            assert_eq!(imported.scheme(), *scheme);
+            assert!(IndraKeyring::from_json(&json, "wrong").is_err());
+        }
+    }
+
+    #[test]
+    fn test_keystore_rejects_costly_scrypt_parameters() {
+        let keyring = IndraKeyring::from_str("//Alice").unwrap();
+        let json = keyring.to_json("secret", 42, "alice").unwrap();
+        let mut value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        let encoded = base64::decode(value["encoded"].as_str().unwrap()).unwrap();
+        // N, p and r follow the 32 byte salt as little endian u32s
+        for (index, param) in &[(0, 1u32 << 20), (1, 2), (2, 16)] {
+            let mut tampered = encoded.clone();
+            let start = SALT_LENGTH + index * 4;
+            tampered[start..start + 4].copy_from_slice(&param.to_le_bytes());
+            value["encoded"] = base64::encode(&tampered).into();
+            let err = IndraKeyring::from_json(value.to_string(), "secret").unwrap_err();
+            assert!(err.to_string().contains("Unsupported scrypt parameters"));
+        }
+    }
+
+    #[test]
+    fn test_keystore_format() {
+        let keyring = IndraKeyring::from_str("//Alice").unwrap();
+        let json = keyring.to_json("secret", 42, "alice").unwrap();
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(
+            value["address"],
+            "5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY"
+        );
+        assert_eq!(value["encoding"]["content"][1], "sr25519");
+        assert_eq!(value["encoding"]["version"], "3");
+        assert_eq!(value["meta"]["name"], "alice");
+    }
+}
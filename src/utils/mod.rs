@@ -1 +1,4 @@
+pub mod address;
 pub mod keyring;
+pub mod keystore;
+pub mod token;
@@ -0,0 +1,287 @@
+use indracore_subxt::{Error, SystemProperties};
+use std::{cmp::Ordering, fmt};
+
+/// A balance of the chain's native token.
+///
+/// The value is kept in planck, the indivisible on-chain unit, together with the
+/// `token_decimals` and `token_symbol` needed to read and write it in whole tokens.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TokenAmount {
+    planck: u128,
+    decimals: u8,
+    symbol: String,
+}
+
+impl TokenAmount {
+    pub fn from_planck(planck: u128, decimals: u8, symbol: impl Into<String>) -> TokenAmount {
+        Self {
+            planck,
+            decimals,
+            symbol: symbol.into(),
+        }
+    }
+
+    /// Creates an amount denominated in the native token of a chain.
+    pub fn with_properties(planck: u128, properties: &SystemProperties) -> TokenAmount {
+        Self::from_planck(planck, properties.token_decimals, &properties.token_symbol)
+    }
+
+    /// Parses a human readable amount such as `"12.5"` or `"12.5 SEL"`.
+    ///
+    /// A symbol, if given, must match `symbol`. More fractional digits than `decimals` are an
+    /// error rather than being silently truncated.
+    pub fn parse(
+        input: impl AsRef<str>,
+        decimals: u8,
+        symbol: impl Into<String>,
+    ) -> Result<TokenAmount, Error> {
+        let input = input.as_ref().trim();
+        let symbol = symbol.into();
+        let mut parts = input.split_whitespace();
+        let number = parts
+            .next()
+            .ok_or_else(|| Error::Other("Empty token amount".into()))?;
+        if let Some(unit) = parts.next() {
+            if !unit.eq_ignore_ascii_case(&symbol) {
+                return Err(Error::Other(format!(
+                    "Amount {} is not denominated in {}",
+                    input, symbol
+                )));
+            }
+        }
+        if parts.next().is_some() {
+            return Err(Error::Other(format!("Invalid token amount {}", input)));
+        }
+
+        let invalid = || Error::Other(format!("Invalid token amount {}", input));
+        let overflow = || Error::Other(format!("Token amount {} is too large", input));
+        let (whole, fraction) = match number.find('.') {
+            Some(index) => (&number[..index], &number[index + 1..]),
+            None => (number, ""),
+        };
+        if whole.is_empty() && fraction.is_empty() {
+            return Err(invalid());
+        }
+        if !whole
+            .chars()
+            .chain(fraction.chars())
+            .all(|c| c.is_ascii_digit())
+        {
+            return Err(invalid());
+        }
+        if fraction.len() > decimals as usize {
+            return Err(Error::Other(format!(
+                "Token amount {} has more than {} decimals",
+                input, decimals
+            )));
+        }
+
+        let mut planck: u128 = 0;
+        let padding = decimals as usize - fraction.len();
+        for digit in whole
+            .chars()
+            .chain(fraction.chars())
+            .chain(std::iter::repeat('0').take(padding))
+        {
+            let digit = digit.to_digit(10).ok_or_else(invalid)? as u128;
+            planck = planck
+                .checked_mul(10)
+                .and_then(|value| value.checked_add(digit))
+                .ok_or_else(overflow)?;
+        }
+        Ok(Self::from_planck(planck, decimals, symbol))
+    }
+
+    pub fn planck(&self) -> u128 {
+        self.planck
+    }
+
+    pub fn decimals(&self) -> u8 {
+        self.decimals
+    }
+
+    pub fn symbol(&self) -> &str {
+        &self.symbol
+    }
+
+    /// Formats the amount with at most `precision` fractional digits, rounding down.
+    pub fn format(&self, precision: usize) -> String {
+        let (whole, fraction) = self.split();
+        let fraction = &fraction[..precision.min(fraction.len())];
+        let mut out = whole.to_string();
+        if !fraction.is_empty() {
+            out.push('.');
+            out.push_str(fraction);
+        }
+        if !self.symbol.is_empty() {
+            out.push(' ');
+            out.push_str(&self.symbol);
+        }
+        out
+    }
+
+    /// Adds two amounts of the same token, returning `None` on overflow or when the tokens
+    /// differ.
+    pub fn checked_add(&self, other: &TokenAmount) -> Option<TokenAmount> {
+        self.same_token(other)?;
+        self.map(self.planck.checked_add(other.planck)?)
+    }
+
+    /// Subtracts two amounts of the same token, returning `None` on underflow or when the
+    /// tokens differ.
+    pub fn checked_sub(&self, other: &TokenAmount) -> Option<TokenAmount> {
+        self.same_token(other)?;
+        self.map(self.planck.checked_sub(other.planck)?)
+    }
+
+    /// Multiplies the amount by `factor`, returning `None` on overflow.
+    pub fn checked_mul(&self, factor: u128) -> Option<TokenAmount> {
+        self.map(self.planck.checked_mul(factor)?)
+    }
+
+    /// Divides the amount by `divisor`, returning `None` if `divisor` is zero.
+    pub fn checked_div(&self, divisor: u128) -> Option<TokenAmount> {
+        self.map(self.planck.checked_div(divisor)?)
+    }
+
+    /// Splits the amount into whole tokens and the zero padded fractional digits.
+    fn split(&self) -> (u128, String) {
+        let (whole, fraction) = match 10u128.checked_pow(self.decimals as u32) {
+            Some(unit) => (self.planck / unit, self.planck % unit),
+            None => (0, self.planck),
+        };
+        if self.decimals == 0 {
+            return (whole, String::new());
+        }
+        let fraction = format!("{:0width$}", fraction, width = self.decimals as usize);
+        (whole, fraction)
+    }
+
+    fn same_token(&self, other: &TokenAmount) -> Option<()> {
+        if self.decimals == other.decimals && self.symbol == other.symbol {
+            Some(())
+        } else {
+            None
+        }
+    }
+
+    fn map(&self, planck: u128) -> Option<TokenAmount> {
+        Some(Self::from_planck(
+            planck,
+            self.decimals,
+            self.symbol.clone(),
+        ))
+    }
+}
+
+impl PartialOrd for TokenAmount {
+    fn partial_cmp(&self, other: &TokenAmount) -> Option<Ordering> {
+        self.same_token(other)?;
+        Some(self.planck.cmp(&other.planck))
+    }
+}
+
+impl fmt::Display for TokenAmount {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let precision = match f.precision() {
+            Some(precision) => precision,
+            // print every significant fractional digit
+            None => self.split().1.trim_end_matches('0').len(),
+        };
+        write!(f, "{}", self.format(precision))
+    }
+}
+
+/// An amount given either in planck or as a [`TokenAmount`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Amount {
+    Planck(u128),
+    Token(TokenAmount),
+}
+
+impl Amount {
+    /// Returns the amount in planck, checking a token amount against the chain's token.
+    pub fn to_planck(&self, properties: &SystemProperties) -> Result<u128, Error> {
+        match self {
+            Amount::Planck(planck) => Ok(*planck),
+            Amount::Token(amount) => {
+                if amount.decimals() != properties.token_decimals
+                    || amount.symbol() != properties.token_symbol
+                {
+                    return Err(Error::Other(format!(
+                        "Amount {} is not denominated in {}",
+                        amount, properties.token_symbol
+                    )));
+                }
+                Ok(amount.planck())
+            }
+        }
+    }
+}
+
+impl From<u128> for Amount {
+    fn from(planck: u128) -> Self {
+        Amount::Planck(planck)
+    }
+}
+
+impl From<TokenAmount> for Amount {
+    fn from(amount: TokenAmount) -> Self {
+        Amount::Token(amount)
+    }
+}
+
+impl From<&TokenAmount> for Amount {
+    fn from(amount: &TokenAmount) -> Self {
+        Amount::Token(amount.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let amount = TokenAmount::parse("12.5 SEL", 12, "SEL").unwrap();
+        assert_eq!(amount.planck(), 12_500_000_000_000);
+        assert_eq!(
+            TokenAmount::parse("0.000000000001", 12, "SEL")
+                .unwrap()
+                .planck(),
+            1
+        );
+        assert_eq!(TokenAmount::parse("7", 0, "SEL").unwrap().planck(), 7);
+        assert!(TokenAmount::parse("12.5 DOT", 12, "SEL").is_err());
+        assert!(TokenAmount::parse("0.0000000000001", 12, "SEL").is_err());
+        assert!(TokenAmount::parse("1e3", 12, "SEL").is_err());
+        assert!(TokenAmount::parse(".", 12, "SEL").is_err());
+        assert!(TokenAmount::parse("999999999999999999999999999999", 12, "SEL").is_err());
+    }
+
+    #[test]
+    fn test_format() {
+        let amount = TokenAmount::from_planck(12_345_600_000_000, 12, "SEL");
+        assert_eq!(amount.to_string(), "12.3456 SEL");
+        assert_eq!(format!("{:.2}", amount), "12.34 SEL");
+        assert_eq!(amount.format(0), "12 SEL");
+        assert_eq!(TokenAmount::from_planck(5, 0, "").to_string(), "5");
+        assert_eq!(TokenAmount::from_planck(0, 12, "SEL").to_string(), "0 SEL");
+    }
+
+    #[test]
+    fn test_checked_arithmetic() {
+        let one = TokenAmount::parse("1", 12, "SEL").unwrap();
+        let half = TokenAmount::parse("0.5", 12, "SEL").unwrap();
+        assert_eq!(one.checked_sub(&half), Some(half.clone()));
+        assert_eq!(half.checked_add(&half), Some(one.clone()));
+        assert_eq!(half.checked_sub(&one), None);
+        assert_eq!(one.checked_mul(u128::MAX), None);
+        assert_eq!(one.checked_div(2), Some(half.clone()));
+        assert_eq!(
+            one.checked_add(&TokenAmount::from_planck(1, 10, "DOT")),
+            None
+        );
+        assert!(half < one);
+    }
+}